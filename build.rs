@@ -1,7 +1,36 @@
 fn main() {
+    if std::env::var_os("CARGO_FEATURE_LIBJXL").is_some() {
+        println!("cargo:rerun-if-env-changed=LIBJXL_LIB_DIR");
+        if let Some(dir) = std::env::var_os("LIBJXL_LIB_DIR") {
+            println!("cargo:rustc-link-search=native={}", dir.to_string_lossy());
+        }
+        println!("cargo:rustc-link-lib=jxl");
+        println!("cargo:rustc-link-lib=jxl_threads");
+    }
+
+    // constants (e.g. Exif tag numbers) are only relevant to the Rust API
+    let mut config = cbindgen::Config::default();
+    config.export.item_types = vec![
+        cbindgen::ItemType::Enums,
+        cbindgen::ItemType::Structs,
+        cbindgen::ItemType::Typedefs,
+        cbindgen::ItemType::OpaqueItems,
+        cbindgen::ItemType::Functions,
+    ];
+    // enums that the FFI takes as plain integers (to validate them), but which C still needs
+    config.export.include = vec![
+        "Preset".to_string(),
+        "Transform".to_string(),
+        "Gravity".to_string(),
+        "Filter".to_string(),
+    ];
+
     cbindgen::Builder::new()
+        .with_config(config)
         .with_crate(std::env::var("CARGO_MANIFEST_DIR").unwrap())
         .with_language(cbindgen::Language::C)
+        // only available when built with the `color` feature
+        .with_define("feature", "color", "WIMG_COLOR")
         .generate()
         .expect("Unable to generate bindings")
         .write_to_file("wimg.h");
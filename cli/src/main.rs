@@ -1,6 +1,6 @@
 use std::collections::BTreeMap;
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::Instant;
@@ -8,6 +8,11 @@ use std::{env, error, fmt, fs, process};
 
 use clap::Parser;
 use parallel::*;
+use wimg::crop::{FocalPoint, Gravity};
+use wimg::metadata::Allowlist;
+use wimg::resize::{Filter, ResizeOptions};
+use wimg::transform::Transform;
+use wimg::ImageFormat;
 
 #[derive(Debug, Parser)]
 #[clap(about, version, author)]
@@ -22,12 +27,12 @@ struct Args {
     base_dir: Option<PathBuf>,
 
     /// The width the images should be resized to.
-    #[clap(long, short)]
-    width: u32,
+    #[clap(long, short, required_unless_present = "favicon")]
+    width: Option<u32>,
 
     /// The height the images should be resized to.
-    #[clap(long, short)]
-    height: u32,
+    #[clap(long, short, required_unless_present = "favicon")]
+    height: Option<u32>,
 
     #[clap(long, short = 'd', default_value = "1")]
     pixel_density: Vec<u8>,
@@ -47,6 +52,51 @@ struct Args {
     #[clap(long)]
     maintain_aspect: bool,
 
+    /// Where to crop the images if their aspect changes: center, north, northeast, east,
+    /// southeast, south, southwest, west, northwest or attention (the most interesting region,
+    /// judged by edges, saturation and skin tones).
+    #[clap(long, default_value = "center", value_parser = parse_gravity, conflicts_with = "maintain_aspect")]
+    gravity: Gravity,
+
+    /// The point `x,y` (normalized to 0..1) to keep in the center when cropping the images (as far
+    /// as possible).
+    #[clap(long, value_parser = parse_focal_point, conflicts_with_all = ["maintain_aspect", "gravity"])]
+    focal_point: Option<FocalPoint>,
+
+    /// The resampling filter: point, triangle, catmullrom, mitchell or lanczos3.
+    #[clap(long, default_value = "triangle", value_parser = parse_filter)]
+    filter: Filter,
+
+    /// Convert the images to sRGB, according to their embedded color profile.
+    #[clap(long)]
+    srgb: bool,
+
+    /// Metadata to keep, as comma-separated list of `artist`, `copyright`, `description`, `make`,
+    /// `model`, `date`, `gps`, `xmp` or Exif tag numbers (e.g. `0x8298`). All other metadata is
+    /// stripped.
+    #[clap(long, value_delimiter = ',', value_parser = parse_keep_metadata)]
+    keep_metadata: Vec<KeepMetadata>,
+
+    /// Rotate the images clockwise by 90, 180 or 270 degrees (after applying their Exif
+    /// orientation).
+    #[clap(long, value_parser = parse_rotate)]
+    rotate: Option<Transform>,
+
+    /// Flip the images horizontally or vertically (after rotating them).
+    #[clap(long, value_parser = parse_flip)]
+    flip: Option<Transform>,
+
+    /// Crop the images to the rectangle `x,y,width,height` (in pixels from the top left) before
+    /// resizing them. Applied after rotating and flipping.
+    #[clap(long, value_parser = parse_crop)]
+    crop: Option<CropRect>,
+
+    /// Generate the standard favicon set (favicon.ico, apple-touch-icon.png, icon-192.png and
+    /// icon-512.png) from a single input image of at least 512x512 px instead. Non-square images
+    /// are cropped.
+    #[clap(long, conflicts_with_all = ["width", "height", "format", "manifest", "maintain_aspect"])]
+    favicon: bool,
+
     #[clap(flatten)]
     jpeg: JpegOptions,
 
@@ -55,6 +105,9 @@ struct Args {
 
     #[clap(flatten)]
     avif: AvifOptions,
+
+    #[clap(flatten)]
+    jxl: JxlOptions,
 }
 
 #[derive(Debug, clap::Args)]
@@ -69,6 +122,27 @@ pub struct WebpOptions {
     /// 0-100 scale
     #[clap(name = "webp-quality", long, default_value = "80")]
     pub quality: u16,
+    /// Encode WebPs without any loss of pixel data.
+    #[clap(name = "webp-lossless", long)]
+    pub lossless: bool,
+    /// 0-100 scale, 100 turns it off (implies --webp-lossless when set below 100)
+    #[clap(name = "webp-near-lossless", long, default_value = "100")]
+    pub near_lossless: u8,
+    /// Preserve the RGB values under fully transparent pixels.
+    #[clap(name = "webp-exact", long)]
+    pub exact: bool,
+    /// 0 (fast) - 6 (slow but better compression)
+    #[clap(name = "webp-method", long, default_value = "4")]
+    pub method: u8,
+    /// photo, picture, drawing, icon or text
+    #[clap(name = "webp-preset", long, default_value = "photo", value_parser = parse_webp_preset)]
+    pub preset: wimg::webp::Preset,
+    /// 0-100 scale
+    #[clap(name = "webp-alpha-quality", long, default_value = "100")]
+    pub alpha_quality: u16,
+    /// Use the slower but more accurate RGB to YUV conversion.
+    #[clap(name = "webp-sharp-yuv", long)]
+    pub use_sharp_yuv: bool,
 }
 
 #[derive(Debug, clap::Args)]
@@ -81,11 +155,28 @@ pub struct AvifOptions {
     pub speed: u8,
 }
 
+#[derive(Debug, clap::Args)]
+pub struct JxlOptions {
+    /// 0-100 scale (lossy encoding requires building with the `libjxl` feature)
+    #[clap(name = "jxl-quality", long, default_value = "80")]
+    pub quality: u16,
+    /// 1 (fast) - 9 (slow but better compression)
+    #[clap(name = "jxl-effort", long, default_value = "7")]
+    pub effort: u8,
+    /// Encode JPEG XLs without any loss of pixel data.
+    #[clap(name = "jxl-lossless", long)]
+    pub lossless: bool,
+}
+
 #[derive(Debug, Clone)]
 enum OutputFormat {
     Avif,
     Jpeg,
+    Jxl,
+    Pam,
     Png,
+    Ppm,
+    Qoi,
     Webp,
 }
 
@@ -106,7 +197,7 @@ fn main() {
         .init();
     let start = Instant::now();
 
-    if args.format.is_empty() {
+    if args.format.is_empty() && !args.favicon {
         log::error!("no output format specified");
         process::exit(1);
     }
@@ -200,6 +291,36 @@ fn main() {
         process::exit(1);
     }
 
+    let allowlist = allowlist(&args.keep_metadata);
+    let transforms = args.rotate.into_iter().chain(args.flip).collect::<Vec<_>>();
+    let resize_options = ResizeOptions {
+        maintain_aspect: args.maintain_aspect,
+        gravity: args.gravity,
+        focal_point: args.focal_point,
+        filter: args.filter,
+    };
+    if args.favicon {
+        let [path] = images.as_slice() else {
+            log::error!("--favicon requires exactly one input image");
+            process::exit(1);
+        };
+        favicon(
+            path,
+            &args.out_dir,
+            args.srgb,
+            &allowlist,
+            &transforms,
+            args.crop,
+            &resize_options,
+        );
+        log::debug!("Took: {:?}", start.elapsed());
+        return;
+    }
+    let (width, height) = (
+        args.width.unwrap_or_default(),
+        args.height.unwrap_or_default(),
+    );
+
     images.par_iter().for_each(|path| {
         let path_string = path.to_string_lossy();
         log::debug!("Processing {}", path_string);
@@ -211,22 +332,7 @@ fn main() {
             }
         };
 
-        let result = match path.extension().and_then(|e| e.to_str()) {
-            Some("jpg") => wimg::jpeg::decode(&data),
-            Some("png") => wimg::png::decode(&data),
-            Some(ext) => {
-                log::error!("unsupported image format: {}", ext);
-                process::exit(1);
-            }
-            None => {
-                log::error!(
-                    "{} must have an extension to guess the image format from",
-                    path_string
-                );
-                process::exit(1);
-            }
-        };
-        let image = match result {
+        let image = match decode(&data, args.srgb, &allowlist, &transforms, args.crop) {
             Ok(image) => Arc::new(RwLock::new(image)),
             Err(err) => {
                 log::error!("failed to decode {}: {}", path_string, err);
@@ -242,11 +348,11 @@ fn main() {
 
             log::debug!("Resizing {} (PD: {})", path_string, pd);
             let image = image.read().unwrap();
-            let image = match wimg::resize::resize(
+            let image = match wimg::resize::resize_with_options(
                 &image,
-                args.width * pd as u32,
-                args.height * pd as u32,
-                args.maintain_aspect,
+                width * pd as u32,
+                height * pd as u32,
+                &resize_options,
             ) {
                 Ok(image) => image,
                 Err(err) => {
@@ -264,7 +370,10 @@ fn main() {
                     + match format {
                         OutputFormat::Avif => wimg::avif::seed(),
                         OutputFormat::Jpeg => wimg::jpeg::seed(),
+                        OutputFormat::Jxl => wimg::jxl::seed(),
+                        OutputFormat::Pam | OutputFormat::Ppm => wimg::pnm::seed(),
                         OutputFormat::Png => wimg::png::seed(),
+                        OutputFormat::Qoi => wimg::qoi::seed(),
                         OutputFormat::Webp => wimg::webp::seed(),
                     };
                 let mut hash = wimg::hash::hash(&data, seed);
@@ -300,7 +409,11 @@ fn main() {
                 let result = match format {
                     OutputFormat::Avif => wimg::avif::encode(&image, &(&args.avif).into()),
                     OutputFormat::Jpeg => wimg::jpeg::encode(&image, &(&args.jpeg).into()),
+                    OutputFormat::Jxl => wimg::jxl::encode(&image, &(&args.jxl).into()),
+                    OutputFormat::Pam => wimg::pnm::encode_pam(&image),
                     OutputFormat::Png => wimg::png::encode(&image),
+                    OutputFormat::Ppm => wimg::pnm::encode_ppm(&image),
+                    OutputFormat::Qoi => wimg::qoi::encode(&image),
                     OutputFormat::Webp => wimg::webp::encode(&image, &(&args.webp).into()),
                 };
                 let image = match result {
@@ -359,12 +472,159 @@ fn main() {
     log::debug!("Took: {:?}", start.elapsed());
 }
 
+/// Sizes embedded into the favicon.ico.
+const FAVICON_ICO_SIZES: [u32; 4] = [16, 32, 48, 256];
+
+/// Additional PNG icons for touch devices and web app manifests.
+const FAVICON_PNGS: [(&str, u32); 3] = [
+    ("apple-touch-icon.png", 180),
+    ("icon-192.png", 192),
+    ("icon-512.png", 512),
+];
+
+/// The minimum size of the image favicons are generated from, as images are never upscaled.
+const FAVICON_MIN_SIZE: u32 = 512;
+
+/// Decode an image of any supported format, keeping only the metadata in `allowlist`, applying
+/// `transforms` and `crop` and optionally converting it to sRGB.
+fn decode(
+    data: &[u8],
+    srgb: bool,
+    allowlist: &Allowlist,
+    transforms: &[Transform],
+    crop: Option<CropRect>,
+) -> Result<wimg::Image, wimg::error::Error> {
+    // only the JPEG and PNG decoders read metadata
+    let mut image = match wimg::detect_format(data) {
+        Some(ImageFormat::JPEG) => wimg::jpeg::decode_with_options(
+            data,
+            &wimg::jpeg::DecodeOptions {
+                keep_metadata: allowlist.clone(),
+                ..Default::default()
+            },
+        )?,
+        Some(ImageFormat::PNG) => wimg::png::decode_with_options(
+            data,
+            &wimg::png::DecodeOptions {
+                keep_metadata: allowlist.clone(),
+                ..Default::default()
+            },
+        )?,
+        _ => wimg::decode(data)?,
+    };
+    for transform in transforms {
+        image = wimg::transform::transform(&image, *transform)?;
+    }
+    if let Some(CropRect {
+        x,
+        y,
+        width,
+        height,
+    }) = crop
+    {
+        image = wimg::crop::crop_rect(&image, x, y, width, height)?;
+    }
+    if srgb {
+        wimg::color::to_srgb(image)
+    } else {
+        Ok(image)
+    }
+}
+
+fn favicon(
+    path: &Path,
+    out_dir: &Path,
+    srgb: bool,
+    allowlist: &Allowlist,
+    transforms: &[Transform],
+    crop: Option<CropRect>,
+    resize_options: &ResizeOptions,
+) {
+    let path_string = path.to_string_lossy();
+    log::debug!("Generating favicons from {}", path_string);
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(err) => {
+            log::error!("failed to read {} ({})", path_string, err);
+            process::exit(1);
+        }
+    };
+    let image = match decode(&data, srgb, allowlist, transforms, crop) {
+        Ok(image) => image,
+        Err(err) => {
+            log::error!("failed to decode {}: {}", path_string, err);
+            process::exit(1);
+        }
+    };
+    if image.width() < FAVICON_MIN_SIZE || image.height() < FAVICON_MIN_SIZE {
+        log::error!(
+            "{} is too small for favicons ({}x{}, at least {FAVICON_MIN_SIZE}x{FAVICON_MIN_SIZE} \
+             px are required)",
+            path_string,
+            image.width(),
+            image.height(),
+        );
+        process::exit(1);
+    }
+
+    // icons are square, so crop instead of maintaining the aspect ratio
+    let resize_options = ResizeOptions {
+        maintain_aspect: false,
+        ..resize_options.clone()
+    };
+    let resize =
+        |size: u32| match wimg::resize::resize_with_options(&image, size, size, &resize_options) {
+            Ok(image) => image,
+            Err(err) => {
+                log::error!("failed to resize {}: {}", path_string, err);
+                process::exit(1);
+            }
+        };
+
+    if let Err(err) = fs::create_dir_all(out_dir) {
+        log::error!(
+            "failed to create directory {}: {}",
+            out_dir.to_string_lossy(),
+            err
+        );
+        process::exit(1);
+    }
+
+    let icons = FAVICON_ICO_SIZES.map(resize);
+    let mut files = vec![("favicon.ico", wimg::ico::encode(&icons))];
+    files.extend(
+        FAVICON_PNGS
+            .iter()
+            .map(|(name, size)| (*name, wimg::png::encode(&resize(*size)))),
+    );
+
+    for (name, result) in files {
+        let out_file = out_dir.join(name);
+        log::debug!("Writing to {}", out_file.to_string_lossy());
+        let image = match result {
+            Ok(image) => image,
+            Err(err) => {
+                log::error!("failed to encode {}: {}", name, err);
+                process::exit(1);
+            }
+        };
+        if let Err(err) = fs::write(&out_file, &image) {
+            log::error!("failed to write {}: {}", out_file.to_string_lossy(), err);
+            process::exit(1);
+        }
+    }
+}
+
 impl OutputFormat {
     fn ext(&self) -> &'static str {
         match self {
             OutputFormat::Avif => "avif",
             OutputFormat::Jpeg => "jpg",
+            OutputFormat::Jxl => "jxl",
+            OutputFormat::Pam => "pam",
             OutputFormat::Png => "png",
+            OutputFormat::Ppm => "ppm",
+            OutputFormat::Qoi => "qoi",
             OutputFormat::Webp => "webp",
         }
     }
@@ -373,7 +633,11 @@ impl OutputFormat {
         match self {
             OutputFormat::Avif => "image/avif",
             OutputFormat::Jpeg => "image/jpeg",
+            OutputFormat::Jxl => "image/jxl",
+            OutputFormat::Pam => "image/x-portable-arbitrarymap",
             OutputFormat::Png => "image/png",
+            OutputFormat::Ppm => "image/x-portable-pixmap",
+            OutputFormat::Qoi => "image/qoi",
             OutputFormat::Webp => "image/webp",
         }
     }
@@ -392,7 +656,11 @@ impl FromStr for OutputFormat {
         Ok(match s.to_ascii_lowercase().as_str() {
             "avif" => OutputFormat::Avif,
             "jpg" | "jpeg" => OutputFormat::Jpeg,
+            "jxl" => OutputFormat::Jxl,
+            "pam" => OutputFormat::Pam,
             "png" => OutputFormat::Png,
+            "ppm" => OutputFormat::Ppm,
+            "qoi" => OutputFormat::Qoi,
             "webp" => OutputFormat::Webp,
             _ => return Err(ParseOutputFormatError),
         })
@@ -410,6 +678,131 @@ impl fmt::Display for ParseOutputFormatError {
 
 impl error::Error for ParseOutputFormatError {}
 
+#[derive(Debug, Clone, Copy)]
+enum KeepMetadata {
+    Exif(u16),
+    Xmp,
+}
+
+fn parse_keep_metadata(s: &str) -> Result<KeepMetadata, &'static str> {
+    use wimg::exif;
+
+    Ok(KeepMetadata::Exif(match s.to_ascii_lowercase().as_str() {
+        "artist" => exif::ARTIST,
+        "copyright" => exif::COPYRIGHT,
+        "description" => exif::IMAGE_DESCRIPTION,
+        "make" => exif::MAKE,
+        "model" => exif::MODEL,
+        "date" => exif::DATE_TIME_ORIGINAL,
+        "gps" => exif::GPS_IFD,
+        "xmp" => return Ok(KeepMetadata::Xmp),
+        s => match s.strip_prefix("0x") {
+            Some(hex) => u16::from_str_radix(hex, 16),
+            None => s.parse(),
+        }
+        .map_err(|_| "invalid metadata name or Exif tag")?,
+    }))
+}
+
+fn allowlist(keep: &[KeepMetadata]) -> Allowlist {
+    let mut allowlist = Allowlist::default();
+    for keep in keep {
+        match keep {
+            KeepMetadata::Exif(tag) => allowlist.exif_tags.push(*tag),
+            KeepMetadata::Xmp => allowlist.xmp = true,
+        }
+    }
+    allowlist
+}
+
+fn parse_rotate(s: &str) -> Result<Transform, &'static str> {
+    Ok(match s {
+        "90" => Transform::Rotate90,
+        "180" => Transform::Rotate180,
+        "270" => Transform::Rotate270,
+        _ => return Err("invalid rotation, expected 90, 180 or 270"),
+    })
+}
+
+fn parse_flip(s: &str) -> Result<Transform, &'static str> {
+    Ok(match s.to_ascii_lowercase().as_str() {
+        "horizontal" => Transform::FlipHorizontal,
+        "vertical" => Transform::FlipVertical,
+        _ => return Err("invalid flip, expected horizontal or vertical"),
+    })
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CropRect {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+fn parse_gravity(s: &str) -> Result<Gravity, &'static str> {
+    Ok(match s.to_ascii_lowercase().as_str() {
+        "center" | "centre" => Gravity::Center,
+        "north" => Gravity::North,
+        "northeast" => Gravity::NorthEast,
+        "east" => Gravity::East,
+        "southeast" => Gravity::SouthEast,
+        "south" => Gravity::South,
+        "southwest" => Gravity::SouthWest,
+        "west" => Gravity::West,
+        "northwest" => Gravity::NorthWest,
+        "attention" => Gravity::Attention,
+        _ => return Err("invalid gravity"),
+    })
+}
+
+fn parse_filter(s: &str) -> Result<Filter, &'static str> {
+    Ok(match s.to_ascii_lowercase().as_str() {
+        "point" => Filter::Point,
+        "triangle" => Filter::Triangle,
+        "catmullrom" => Filter::CatmullRom,
+        "mitchell" => Filter::Mitchell,
+        "lanczos3" => Filter::Lanczos3,
+        _ => return Err("invalid resampling filter"),
+    })
+}
+
+fn parse_focal_point(s: &str) -> Result<FocalPoint, &'static str> {
+    const ERR: &str = "invalid focal point, expected x,y between 0 and 1";
+
+    let (x, y) = s.split_once(',').ok_or(ERR)?;
+    let parse = |v: &str| {
+        v.trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|v| (0.0..=1.0).contains(v))
+            .ok_or(ERR)
+    };
+    Ok(FocalPoint {
+        x: parse(x)?,
+        y: parse(y)?,
+    })
+}
+
+fn parse_crop(s: &str) -> Result<CropRect, &'static str> {
+    const ERR: &str = "invalid crop, expected x,y,width,height";
+
+    let mut values = s
+        .split(',')
+        .map(|v| v.trim().parse::<u32>().map_err(|_| ERR));
+    let mut next = || values.next().unwrap_or(Err(ERR));
+    let rect = CropRect {
+        x: next()?,
+        y: next()?,
+        width: next()?,
+        height: next()?,
+    };
+    if values.next().is_some() {
+        return Err(ERR);
+    }
+    Ok(rect)
+}
+
 impl<'a> From<&'a JpegOptions> for wimg::jpeg::EncodeOptions {
     fn from(opts: &'a JpegOptions) -> Self {
         Self {
@@ -422,10 +815,30 @@ impl<'a> From<&'a WebpOptions> for wimg::webp::EncodeOptions {
     fn from(opts: &'a WebpOptions) -> Self {
         Self {
             quality: opts.quality,
+            lossless: opts.lossless,
+            near_lossless: opts.near_lossless,
+            exact: opts.exact,
+            method: opts.method,
+            preset: opts.preset,
+            alpha_quality: opts.alpha_quality,
+            use_sharp_yuv: opts.use_sharp_yuv,
         }
     }
 }
 
+fn parse_webp_preset(s: &str) -> Result<wimg::webp::Preset, &'static str> {
+    use wimg::webp::Preset;
+
+    Ok(match s.to_ascii_lowercase().as_str() {
+        "picture" => Preset::Picture,
+        "photo" => Preset::Photo,
+        "drawing" => Preset::Drawing,
+        "icon" => Preset::Icon,
+        "text" => Preset::Text,
+        _ => return Err("invalid WebP preset"),
+    })
+}
+
 impl<'a> From<&'a AvifOptions> for wimg::avif::EncodeOptions {
     fn from(opts: &'a AvifOptions) -> Self {
         Self {
@@ -435,6 +848,17 @@ impl<'a> From<&'a AvifOptions> for wimg::avif::EncodeOptions {
     }
 }
 
+impl<'a> From<&'a JxlOptions> for wimg::jxl::EncodeOptions {
+    fn from(opts: &'a JxlOptions) -> Self {
+        Self {
+            quality: opts.quality,
+            effort: opts.effort,
+            lossless: opts.lossless,
+            ..Default::default()
+        }
+    }
+}
+
 #[cfg(feature = "parallel")]
 mod parallel {
     pub use rayon::prelude::*;
@@ -1,6 +1,6 @@
 #![allow(clippy::missing_safety_doc)]
 
-use std::os::raw::c_int;
+use std::os::raw::{c_int, c_uint};
 use std::{error, fmt, mem};
 
 pub use mozjpeg_sys::{
@@ -66,6 +66,17 @@ extern "C" {
     ) -> JpegResult;
     pub fn try_jpeg_finish_compress(cinfo: &mut jpeg_compress_struct) -> JpegResult;
     pub fn try_jpeg_destroy_compress(cinfo: &mut jpeg_compress_struct) -> JpegResult;
+    pub fn try_jpeg_write_icc_profile(
+        cinfo: &mut jpeg_compress_struct,
+        icc_data_ptr: *const u8,
+        icc_data_len: c_uint,
+    ) -> JpegResult;
+    pub fn try_jpeg_write_marker(
+        cinfo: &mut jpeg_compress_struct,
+        marker: c_int,
+        dataptr: *const u8,
+        datalen: c_uint,
+    ) -> JpegResult;
 
     pub fn try_jpeg_mem_dest(
         cinfo: &mut jpeg_compress_struct,
@@ -50,6 +50,12 @@ extern JDIMENSION jpeg_write_scanlines(struct jpeg_compress_struct *cinfo,
                                        JDIMENSION num_lines);
 extern void jpeg_finish_compress(struct jpeg_compress_struct *cinfo);
 extern void jpeg_destroy_compress(struct jpeg_compress_struct *cinfo);
+extern void jpeg_write_icc_profile(struct jpeg_compress_struct *cinfo,
+                                   const unsigned char *icc_data_ptr,
+                                   unsigned int icc_data_len);
+extern void jpeg_write_marker(struct jpeg_compress_struct *cinfo, int marker,
+                              const unsigned char *dataptr,
+                              unsigned int datalen);
 
 // memory read and write
 extern void jpeg_mem_dest(struct jpeg_compress_struct *cinfo,
@@ -292,6 +298,38 @@ try_jpeg_destroy_compress(struct jpeg_compress_struct *cinfo) {
   return result_ok();
 }
 
+extern struct result
+try_jpeg_write_icc_profile(struct jpeg_compress_struct *cinfo,
+                           const unsigned char *icc_data_ptr,
+                           unsigned int icc_data_len) {
+#ifndef WASM
+  struct wimg_error_mgr *err = (struct wimg_error_mgr *)cinfo->err;
+  if (setjmp(err->setjmp_buffer)) {
+    return result_err((struct jpeg_common_struct *)cinfo);
+  }
+#endif
+
+  jpeg_write_icc_profile(cinfo, icc_data_ptr, icc_data_len);
+
+  return result_ok();
+}
+
+extern struct result try_jpeg_write_marker(struct jpeg_compress_struct *cinfo,
+                                           int marker,
+                                           const unsigned char *dataptr,
+                                           unsigned int datalen) {
+#ifndef WASM
+  struct wimg_error_mgr *err = (struct wimg_error_mgr *)cinfo->err;
+  if (setjmp(err->setjmp_buffer)) {
+    return result_err((struct jpeg_common_struct *)cinfo);
+  }
+#endif
+
+  jpeg_write_marker(cinfo, marker, dataptr, datalen);
+
+  return result_ok();
+}
+
 extern struct result try_jpeg_mem_dest(struct jpeg_compress_struct *cinfo,
                                        unsigned char **outbuffer,
                                        unsigned long *outsize) {
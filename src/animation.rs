@@ -0,0 +1,198 @@
+use crate::{Image, ImageFormat};
+
+/// A sequence of frames, each already composited onto the full canvas.
+#[derive(Debug, Clone)]
+pub struct Animation {
+    frames: Vec<Frame>,
+    width: u32,
+    height: u32,
+    loop_count: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Frame {
+    image: Image,
+    /// display duration in milliseconds
+    delay: u32,
+}
+
+impl Animation {
+    /// Create an empty animation. A `loop_count` of `0` means the animation repeats forever.
+    pub fn new(width: u32, height: u32, loop_count: u32) -> Self {
+        Self {
+            frames: Vec::new(),
+            width,
+            height,
+            loop_count,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn loop_count(&self) -> u32 {
+        self.loop_count
+    }
+
+    pub fn set_loop_count(&mut self, loop_count: u32) {
+        self.loop_count = loop_count;
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn push(&mut self, image: Image, delay: u32) {
+        self.frames.push(Frame { image, delay });
+    }
+
+    pub fn into_frames(self) -> Vec<Frame> {
+        self.frames
+    }
+}
+
+impl Frame {
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    /// Display duration in milliseconds.
+    pub fn delay(&self) -> u32 {
+        self.delay
+    }
+
+    pub fn into_image(self) -> Image {
+        self.image
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Blend {
+    /// Overwrite the canvas region.
+    Source,
+    /// Alpha-composite onto the canvas region.
+    Over,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Dispose {
+    /// Leave the canvas as it is.
+    None,
+    /// Clear the frame region to transparent black.
+    Background,
+    /// Restore the frame region to what it was before the frame was drawn.
+    Previous,
+}
+
+/// RGBA8 canvas frames are drawn onto, following the disposal and blend rules shared by APNG and
+/// GIF.
+pub(crate) struct Canvas {
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+    previous: Option<Vec<u8>>,
+}
+
+/// Position and size of a frame on the canvas.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            data: vec![0; width as usize * height as usize * 4],
+            width,
+            height,
+            previous: None,
+        }
+    }
+
+    /// Draw RGBA8 `pixels` of the size of `region` onto the canvas. Parts outside of the canvas are
+    /// skipped.
+    pub fn draw(&mut self, pixels: &[u8], region: Region, blend: Blend, dispose: Dispose) {
+        self.previous = (dispose == Dispose::Previous).then(|| self.data.clone());
+
+        for (row, y) in (region.y..region.y.saturating_add(region.height))
+            .take_while(|y| *y < self.height)
+            .enumerate()
+        {
+            for (col, x) in (region.x..region.x.saturating_add(region.width))
+                .take_while(|x| *x < self.width)
+                .enumerate()
+            {
+                let from = (row * region.width as usize + col) * 4;
+                let Some(src) = pixels.get(from..from + 4) else {
+                    return;
+                };
+                let to = (y as usize * self.width as usize + x as usize) * 4;
+                let dst = &mut self.data[to..to + 4];
+                match blend {
+                    Blend::Source => dst.copy_from_slice(src),
+                    Blend::Over => over(src, dst),
+                }
+            }
+        }
+    }
+
+    /// Snapshot the current canvas as an RGBA8 image.
+    pub fn snapshot(&self) -> Image {
+        Image::new(
+            self.data.clone(),
+            ImageFormat::RGBA8,
+            self.width,
+            self.height,
+        )
+    }
+
+    /// Dispose the previously drawn frame.
+    pub fn dispose(&mut self, region: Region, dispose: Dispose) {
+        match dispose {
+            Dispose::None => {}
+            Dispose::Background => {
+                for y in (region.y..region.y.saturating_add(region.height))
+                    .take_while(|y| *y < self.height)
+                {
+                    let start = (y as usize * self.width as usize + region.x as usize) * 4;
+                    let end = (y as usize * self.width as usize
+                        + region.x.saturating_add(region.width).min(self.width) as usize)
+                        * 4;
+                    if start < end {
+                        self.data[start..end].fill(0);
+                    }
+                }
+            }
+            Dispose::Previous => {
+                if let Some(previous) = self.previous.take() {
+                    self.data = previous;
+                }
+            }
+        }
+    }
+}
+
+/// Alpha-composite the non-premultiplied `src` pixel over `dst`.
+fn over(src: &[u8], dst: &mut [u8]) {
+    let src_a = u32::from(src[3]);
+    match src_a {
+        0 => {}
+        255 => dst.copy_from_slice(src),
+        _ => {
+            let dst_a = u32::from(dst[3]) * (255 - src_a) / 255;
+            let out_a = src_a + dst_a;
+            for i in 0..3 {
+                dst[i] = ((u32::from(src[i]) * src_a + u32::from(dst[i]) * dst_a) / out_a) as u8;
+            }
+            dst[3] = out_a as u8;
+        }
+    }
+}
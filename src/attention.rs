@@ -0,0 +1,166 @@
+//! Content-aware placement of crop windows. Candidate windows are scored on a downscaled proxy of
+//! the image by their edge density, saturation and skin tones.
+
+use std::borrow::Cow;
+
+use crate::error::Error;
+use crate::{samples16, Image, ImageFormat};
+
+/// The maximum width and height of the proxy the windows are scored on.
+const PROXY_SIZE: u32 = 256;
+
+const EDGE_WEIGHT: f32 = 1.0;
+const SATURATION_WEIGHT: f32 = 0.3;
+const SKIN_WEIGHT: f32 = 1.8;
+
+/// A typical skin tone, normalized to unit length.
+const SKIN_COLOR: [f32; 3] = [0.7348, 0.5369, 0.4145];
+/// The maximum distance (of the normalized color) to `SKIN_COLOR` that still counts as skin.
+const SKIN_THRESHOLD: f32 = 0.1;
+
+/// The offset of the `width`x`height` window covering the most interesting region of the image.
+/// If several windows score the same (e.g. for a plain image), the most central one is used.
+pub(crate) fn offset(img: &Image, width: u32, height: u32) -> Result<(u32, u32), Error> {
+    let free_x = img.width.saturating_sub(width);
+    let free_y = img.height.saturating_sub(height);
+    // leave rejecting empty or oversized windows to `crop_rect`, like for the other gravities
+    let fits = width > 0 && height > 0 && width <= img.width && height <= img.height;
+    if !fits || (free_x == 0 && free_y == 0) {
+        return Ok((free_x / 2, free_y / 2));
+    }
+
+    let scale = f64::from(PROXY_SIZE) / f64::from(img.width.max(img.height));
+    let proxy = if scale < 1.0 {
+        let proxy_width = (f64::from(img.width) * scale).round().max(1.0) as u32;
+        let proxy_height = (f64::from(img.height) * scale).round().max(1.0) as u32;
+        Cow::Owned(crate::resize::resize(img, proxy_width, proxy_height, true)?)
+    } else {
+        Cow::Borrowed(img)
+    };
+
+    let (proxy_width, proxy_height) = (proxy.width as usize, proxy.height as usize);
+    let scores = scores(&pixels(&proxy)?, proxy_width, proxy_height);
+
+    // summed-area table, so that the score of each window is a constant-time lookup
+    let stride = proxy_width + 1;
+    let mut table = vec![0u64; stride * (proxy_height + 1)];
+    for y in 0..proxy_height {
+        let mut row_sum = 0;
+        for x in 0..proxy_width {
+            row_sum += u64::from(scores[y * proxy_width + x]);
+            table[(y + 1) * stride + x + 1] = table[y * stride + x + 1] + row_sum;
+        }
+    }
+
+    // the window in proxy coordinates
+    let scale_x = proxy_width as f64 / f64::from(img.width);
+    let scale_y = proxy_height as f64 / f64::from(img.height);
+    let window_width = ((f64::from(width) * scale_x).round() as usize).clamp(1, proxy_width);
+    let window_height = ((f64::from(height) * scale_y).round() as usize).clamp(1, proxy_height);
+    let (center_x, center_y) = (
+        (proxy_width - window_width) / 2,
+        (proxy_height - window_height) / 2,
+    );
+
+    let mut best = (0, usize::MAX, center_x, center_y);
+    for y in 0..=(proxy_height - window_height) {
+        for x in 0..=(proxy_width - window_width) {
+            let (right, bottom) = (x + window_width, y + window_height);
+            let score = table[bottom * stride + right] + table[y * stride + x]
+                - table[y * stride + right]
+                - table[bottom * stride + x];
+            let distance = x.abs_diff(center_x) + y.abs_diff(center_y);
+            if score > best.0 || (score == best.0 && distance < best.1) {
+                best = (score, distance, x, y);
+            }
+        }
+    }
+
+    let (_, _, x, y) = best;
+    Ok((
+        ((x as f64 / scale_x).round() as u32).min(free_x),
+        ((y as f64 / scale_y).round() as u32).min(free_y),
+    ))
+}
+
+/// The pixels as normalized RGBA.
+fn pixels(img: &Image) -> Result<Vec<[f32; 4]>, Error> {
+    let data: &[u8] = img.as_ref();
+    let pixels = match img.format {
+        ImageFormat::RGB8 => data
+            .chunks_exact(3)
+            .map(|p| [p[0], p[1], p[2]].map(|v| f32::from(v) / 255.0))
+            .map(|[r, g, b]| [r, g, b, 1.0])
+            .collect(),
+        ImageFormat::RGBA8 => data
+            .chunks_exact(4)
+            .map(|p| [p[0], p[1], p[2], p[3]].map(|v| f32::from(v) / 255.0))
+            .collect(),
+        ImageFormat::RGB16 => samples16(data)
+            .chunks_exact(3)
+            .map(|p| [p[0], p[1], p[2]].map(|v| f32::from(v) / 65535.0))
+            .map(|[r, g, b]| [r, g, b, 1.0])
+            .collect(),
+        ImageFormat::RGBA16 => samples16(data)
+            .chunks_exact(4)
+            .map(|p| [p[0], p[1], p[2], p[3]].map(|v| f32::from(v) / 65535.0))
+            .collect(),
+        _ => {
+            return Err(Error::Process {
+                process: "crop",
+                format: img.format,
+            })
+        }
+    };
+    Ok(pixels)
+}
+
+/// Score how interesting each pixel is, scaled to integers so that equal windows compare as such.
+fn scores(pixels: &[[f32; 4]], width: usize, height: usize) -> Vec<u32> {
+    let luma = pixels
+        .iter()
+        .map(|[r, g, b, _]| 0.2126 * r + 0.7152 * g + 0.0722 * b)
+        .collect::<Vec<_>>();
+
+    let mut scores = Vec::with_capacity(pixels.len());
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * width + x;
+            let [r, g, b, a] = pixels[i];
+
+            // Laplacian of the luma, with the edges of the image extended
+            let neighbours = luma[y * width + x.saturating_sub(1)]
+                + luma[y * width + (x + 1).min(width - 1)]
+                + luma[y.saturating_sub(1) * width + x]
+                + luma[(y + 1).min(height - 1) * width + x];
+            let edge = (4.0 * luma[i] - neighbours).abs().min(1.0);
+
+            let max = r.max(g).max(b);
+            let min = r.min(g).min(b);
+            // ignore the noisy saturation of very dark and very bright pixels
+            let saturation = if (0.05..=0.9).contains(&luma[i]) {
+                (max - min) / max
+            } else {
+                0.0
+            };
+
+            let length = (r * r + g * g + b * b).sqrt();
+            let skin = if length > 0.0 && (0.2..=0.95).contains(&luma[i]) {
+                let distance = [r, g, b]
+                    .iter()
+                    .zip(SKIN_COLOR)
+                    .map(|(v, skin)| (v / length - skin).powi(2))
+                    .sum::<f32>()
+                    .sqrt();
+                (1.0 - distance / SKIN_THRESHOLD).max(0.0)
+            } else {
+                0.0
+            };
+
+            let score =
+                a * (EDGE_WEIGHT * edge + SATURATION_WEIGHT * saturation + SKIN_WEIGHT * skin);
+            scores.push((score * 1000.0).round() as u32);
+        }
+    }
+    scores
+}
@@ -1,12 +1,205 @@
+use std::io::Cursor;
+use std::mem::MaybeUninit;
+use std::ptr::NonNull;
+
+use rav1d::include::dav1d::data::Dav1dData;
+use rav1d::include::dav1d::dav1d::{Dav1dContext, Dav1dSettings};
+use rav1d::include::dav1d::headers::{
+    DAV1D_MC_BT2020_CL, DAV1D_MC_BT2020_NCL, DAV1D_MC_BT709, DAV1D_MC_FCC, DAV1D_MC_IDENTITY,
+    DAV1D_MC_SMPTE240, DAV1D_PIXEL_LAYOUT_I400, DAV1D_PIXEL_LAYOUT_I420, DAV1D_PIXEL_LAYOUT_I422,
+};
+use rav1d::include::dav1d::picture::Dav1dPicture;
+use rav1d::src::lib::{
+    dav1d_close, dav1d_data_create, dav1d_data_unref, dav1d_default_settings, dav1d_get_picture,
+    dav1d_open, dav1d_picture_unref, dav1d_send_data,
+};
+use rav1e::prelude::PixelRange;
 use rgb::FromSlice;
 
 use crate::error::Error;
-use crate::{Image, ImageFormat};
+use crate::{samples16, ColorType, Image, ImageFormat, ImageInfo};
 
 pub fn seed() -> u32 {
     1
 }
 
+pub fn decode(data: &[u8]) -> Result<Image, Error> {
+    let avif = avif_parse::read_avif(&mut Cursor::new(data)).map_err(AvifError::from)?;
+
+    let color = Picture::decode(&avif.primary_item)?;
+    let alpha = avif
+        .alpha_item
+        .as_deref()
+        .map(Picture::decode)
+        .transpose()?;
+
+    let (width, height) = (color.width(), color.height());
+    if let Some(alpha) = &alpha {
+        if alpha.width() != width || alpha.height() != height {
+            return Err(AvifError::Decode("alpha plane dimensions do not match image").into());
+        }
+    }
+
+    let pixel_size = if alpha.is_some() { 4 } else { 3 };
+    let mut buffer = Vec::with_capacity(width as usize * height as usize * pixel_size);
+    let yuv = color.yuv_converter();
+    for y in 0..height {
+        for x in 0..width {
+            let mut rgb = yuv.to_rgb(&color, x, y);
+            if let Some(alpha) = &alpha {
+                let a = alpha.luma8(x, y);
+                if avif.premultiplied_alpha {
+                    for c in &mut rgb {
+                        *c = if a == 0 {
+                            0
+                        } else {
+                            ((u16::from(*c) * 255 + u16::from(a) / 2) / u16::from(a)).min(255) as u8
+                        };
+                    }
+                }
+                buffer.extend_from_slice(&rgb);
+                buffer.push(a);
+            } else {
+                buffer.extend_from_slice(&rgb);
+            }
+        }
+    }
+
+    let mut img = Image::new(
+        buffer,
+        if alpha.is_some() {
+            ImageFormat::RGBA8
+        } else {
+            ImageFormat::RGB8
+        },
+        width,
+        height,
+    );
+    img.set_icc_profile(icc_profile(data));
+    Ok(img)
+}
+
+/// Read the basic properties of an AVIF, by only parsing the item properties in its `meta` box.
+pub fn probe(data: &[u8]) -> Result<ImageInfo, Error> {
+    Ok(probe_meta(data).ok_or(AvifError::Decode("missing or invalid item properties"))?)
+}
+
+fn probe_meta(avif: &[u8]) -> Option<ImageInfo> {
+    let item_properties = ItemProperties::parse(avif)?;
+    let primary_item = item_properties.primary_item;
+    let item_properties = |item| item_properties.of(item);
+
+    // `ispe` and `av1C` are mandatory
+    let ispe = item_properties(primary_item).find(|(fourcc, _)| fourcc == b"ispe")?;
+    let (width, height) = (
+        u32_at(avif, ispe.1.start + 12)?,
+        u32_at(avif, ispe.1.start + 16)?,
+    );
+    let av1c = item_properties(primary_item).find(|(fourcc, _)| fourcc == b"av1C")?;
+    let flags = *avif.get(av1c.1.start + 10)?;
+    let (high_bitdepth, twelve_bit, monochrome) =
+        (flags & 0x40 != 0, flags & 0x20 != 0, flags & 0x10 != 0);
+
+    // an auxiliary image of type alpha referencing the primary item
+    let mut has_alpha = false;
+    let meta = find_box(avif, 0..avif.len(), b"meta")?;
+    if let Some(iref) = find_box(avif, meta.start + 12..meta.end, b"iref") {
+        let id_size = if *avif.get(iref.start + 8)? == 0 {
+            2
+        } else {
+            4
+        };
+        let id_at = |pos| match id_size {
+            2 => u16_at(avif, pos).map(u32::from),
+            _ => u32_at(avif, pos),
+        };
+        for (fourcc, reference) in boxes(avif, iref.start + 12..iref.end) {
+            if &fourcc != b"auxl" {
+                continue;
+            }
+            let from = id_at(reference.start + 8)?;
+            let count = usize::from(u16_at(avif, reference.start + 8 + id_size)?);
+            let to_primary = (0..count)
+                .map(|i| id_at(reference.start + 10 + id_size * (i + 1)))
+                .any(|to| to == Some(primary_item));
+            let is_alpha = item_properties(from).any(|(fourcc, auxc)| {
+                fourcc == b"auxC"
+                    && avif.get(auxc.start + 12..auxc.end).is_some_and(|urn| {
+                        urn.starts_with(b"urn:mpeg:mpegB:cicp:systems:auxiliary:alpha")
+                            || urn.starts_with(b"urn:mpeg:hevc:2015:auxid:1")
+                    })
+            });
+            has_alpha |= to_primary && is_alpha;
+        }
+    }
+
+    Some(ImageInfo {
+        width,
+        height,
+        format: ImageFormat::AVIF,
+        color_type: match (monochrome, has_alpha) {
+            (true, false) => ColorType::Gray,
+            (true, true) => ColorType::GrayAlpha,
+            (false, false) => ColorType::RGB,
+            (false, true) => ColorType::RGBA,
+        },
+        bit_depth: match (high_bitdepth, twelve_bit) {
+            (false, _) => 8,
+            (true, false) => 10,
+            (true, true) => 12,
+        },
+        has_alpha,
+        orientation: 1,
+    })
+}
+
+/// Read the ICC profile of the primary item, i.e. its `colr` property of type `prof`.
+fn icc_profile(avif: &[u8]) -> Option<Vec<u8>> {
+    let item_properties = ItemProperties::parse(avif)?;
+    let (_, colr) = item_properties
+        .of(item_properties.primary_item)
+        .find(|(fourcc, colr)| {
+            fourcc == b"colr"
+                && avif.get(colr.clone()).and_then(|colr| colr.get(8..12)) == Some(b"prof")
+        })?;
+    Some(avif.get(colr.start + 12..colr.end)?.to_vec())
+}
+
+/// The item properties in the `meta` box of an AVIF.
+struct ItemProperties {
+    primary_item: u32,
+    /// The properties in `ipco`, as fourcc and range
+    properties: Vec<([u8; 4], std::ops::Range<usize>)>,
+    associations: Vec<Association>,
+}
+
+impl ItemProperties {
+    fn parse(avif: &[u8]) -> Option<Self> {
+        let meta = find_box(avif, 0..avif.len(), b"meta")?;
+        let meta_content = meta.start + 12..meta.end;
+        let primary_item = primary_item(avif, meta_content.clone())?;
+        let iprp = find_box(avif, meta_content, b"iprp")?;
+        let ipco = find_box(avif, iprp.start + 8..iprp.end, b"ipco")?;
+        let ipma = find_box(avif, iprp.start + 8..iprp.end, b"ipma")?;
+
+        Some(Self {
+            primary_item,
+            properties: boxes(avif, ipco.start + 8..ipco.end).collect(),
+            associations: associations(avif, ipma)?,
+        })
+    }
+
+    /// The properties of `item`, as fourcc and range.
+    fn of(&self, item: u32) -> impl Iterator<Item = &([u8; 4], std::ops::Range<usize>)> {
+        self.associations
+            .iter()
+            .filter(move |association| association.item == item)
+            .flat_map(|association| &association.properties)
+            // property indices are 1-based, with 0 meaning none
+            .filter_map(|index| self.properties.get(index.checked_sub(1)?))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct EncodeOptions {
     /// 0-100 scale
@@ -22,40 +215,88 @@ pub fn encode(img: &Image, opts: &EncodeOptions) -> Result<Image, Error> {
         .with_speed(opts.speed)
         .with_internal_color_model(ravif::ColorModel::YCbCr)
         .with_num_threads(cfg!(target_family = "wasm").then_some(1));
-    match img.format {
+    let ravif::EncodedImage { avif_file, .. } = match img.format {
         ImageFormat::RGB8 => {
             let input = ravif::Img::new(
                 img.as_ref().as_rgb(),
                 img.width as usize,
                 img.height as usize,
             );
-            let ravif::EncodedImage { avif_file, .. } =
-                enc.encode_rgb(input).map_err(Error::Avif)?;
-            Ok(Image::new(
-                avif_file,
-                ImageFormat::AVIF,
-                img.width,
-                img.height,
-            ))
+            enc.encode_rgb(input).map_err(AvifError::from)?
         }
         ImageFormat::RGBA8 => {
             let data = img.as_ref().as_rgba().to_vec();
             let input = ravif::Img::new(data, img.width as usize, img.height as usize);
             let enc = enc.with_alpha_color_mode(ravif::AlphaColorMode::UnassociatedClean);
-            let ravif::EncodedImage { avif_file, .. } =
-                enc.encode_rgba(input.as_ref()).map_err(Error::Avif)?;
-            Ok(Image::new(
-                avif_file,
-                ImageFormat::AVIF,
-                img.width,
-                img.height,
-            ))
+            enc.encode_rgba(input.as_ref()).map_err(AvifError::from)?
         }
-        _ => Err(Error::Process {
-            process: "encode as AVIF",
-            format: img.format,
-        }),
-    }
+        ImageFormat::RGB16 | ImageFormat::RGBA16 => encode_10bit(&enc, img)?,
+        _ => {
+            return Err(Error::Process {
+                process: "encode as AVIF",
+                format: img.format,
+            })
+        }
+    };
+
+    let avif_file = match img.icc_profile() {
+        Some(icc_profile) => {
+            embed_icc_profile(&avif_file, icc_profile).ok_or(AvifError::EmbedIccProfile)?
+        }
+        None => avif_file,
+    };
+    let avif_file = match img.metadata() {
+        Some(metadata) => embed_metadata(
+            &avif_file,
+            metadata.exif.as_deref(),
+            metadata.xmp.as_deref(),
+        )
+        .ok_or(AvifError::EmbedMetadata)?,
+        None => avif_file,
+    };
+    Ok(Image::new(
+        avif_file,
+        ImageFormat::AVIF,
+        img.width,
+        img.height,
+    ))
+}
+
+/// Encode RGB16/RGBA16 images as 10-bit AVIF, by converting them to full range BT.601 YCbCr
+/// (the same matrix ravif uses for 8-bit input).
+fn encode_10bit(enc: &ravif::Encoder, img: &Image) -> Result<ravif::EncodedImage, Error> {
+    const KR: f32 = 0.299;
+    const KB: f32 = 0.114;
+    const MAX: f32 = 1023.0;
+
+    let channels = match img.format {
+        ImageFormat::RGBA16 => 4,
+        _ => 3,
+    };
+    let samples = samples16(img.as_ref());
+    let planes = samples.chunks_exact(channels).map(|px| {
+        let [r, g, b] = [px[0], px[1], px[2]].map(|v| f32::from(v) / 65535.0);
+        let y = KR * r + (1.0 - KR - KB) * g + KB * b;
+        let cb = (b - y) / (2.0 - 2.0 * KB) + 0.5;
+        let cr = (r - y) / (2.0 - 2.0 * KR) + 0.5;
+        [y, cb, cr].map(|v| (v.clamp(0.0, 1.0) * MAX + 0.5) as u16)
+    });
+    let alpha = (channels == 4).then(|| {
+        samples
+            .chunks_exact(4)
+            .map(|px| ((u32::from(px[3]) * 1023 + 32767) / 65535) as u16)
+    });
+
+    Ok(enc
+        .encode_raw_planes_10_bit(
+            img.width as usize,
+            img.height as usize,
+            planes,
+            alpha,
+            PixelRange::Full,
+            ravif::MatrixCoefficients::BT601,
+        )
+        .map_err(AvifError::from)?)
 }
 
 impl Default for EncodeOptions {
@@ -66,3 +307,664 @@ impl Default for EncodeOptions {
         }
     }
 }
+
+/// `DAV1D_ERR(EAGAIN)`, returned when the decoder needs more data or cannot take any yet.
+const DAV1D_EAGAIN: i32 = -libc::EAGAIN;
+
+/// A decoded AV1 frame, unreferenced when dropped.
+struct Picture(Dav1dPicture);
+
+impl Picture {
+    fn decode(obu: &[u8]) -> Result<Self, Error> {
+        unsafe {
+            let mut settings = MaybeUninit::<Dav1dSettings>::uninit();
+            dav1d_default_settings(NonNull::new_unchecked(settings.as_mut_ptr()));
+            let mut settings = settings.assume_init();
+            settings.max_frame_delay = 1;
+            if cfg!(target_family = "wasm") {
+                settings.n_threads = 1;
+            }
+
+            let mut ctx: Option<Dav1dContext> = None;
+            if dav1d_open(
+                Some(NonNull::from(&mut ctx)),
+                Some(NonNull::from(&mut settings)),
+            )
+            .0 != 0
+            {
+                return Err(AvifError::Decode("failed to initialize decoder").into());
+            }
+
+            let mut data = Dav1dData::default();
+            let buf = dav1d_data_create(Some(NonNull::from(&mut data)), obu.len());
+            if buf.is_null() {
+                dav1d_close(Some(NonNull::from(&mut ctx)));
+                return Err(Error::ExceedsMemory);
+            }
+            std::ptr::copy_nonoverlapping(obu.as_ptr(), buf, obu.len());
+
+            // The decoder refuses data while it still holds unconsumed data, and asks for more
+            // data before it has a picture ready, both with EAGAIN. Once all data is sent, a
+            // further call drains any frame that is still in flight.
+            let mut picture = Dav1dPicture::default();
+            let mut draining = false;
+            let result = loop {
+                if data.sz > 0 {
+                    let res = dav1d_send_data(ctx, Some(NonNull::from(&mut data))).0;
+                    if res != 0 && res != DAV1D_EAGAIN {
+                        break Err(AvifError::Decode("failed to send data to decoder"));
+                    }
+                }
+                match dav1d_get_picture(ctx, Some(NonNull::from(&mut picture))).0 {
+                    0 => break Ok(()),
+                    DAV1D_EAGAIN if data.sz > 0 => {}
+                    DAV1D_EAGAIN if !draining => draining = true,
+                    DAV1D_EAGAIN => break Err(AvifError::Decode("data contains no picture")),
+                    _ => break Err(AvifError::Decode("failed to decode picture")),
+                }
+            };
+
+            dav1d_data_unref(Some(NonNull::from(&mut data)));
+            dav1d_close(Some(NonNull::from(&mut ctx)));
+
+            let picture = Picture(picture);
+            result?;
+            if picture.0.seq_hdr.is_none() || picture.0.data[0].is_none() {
+                return Err(AvifError::Decode("decoder returned an empty picture").into());
+            }
+
+            Ok(picture)
+        }
+    }
+
+    fn width(&self) -> u32 {
+        self.0.p.w as u32
+    }
+
+    fn height(&self) -> u32 {
+        self.0.p.h as u32
+    }
+
+    fn bit_depth(&self) -> u32 {
+        self.0.p.bpc as u32
+    }
+
+    fn full_range(&self) -> bool {
+        unsafe { self.0.seq_hdr.unwrap().as_ref().color_range != 0 }
+    }
+
+    /// Read a single sample of the given plane (0 = Y, 1 = U, 2 = V).
+    fn sample(&self, plane: usize, x: u32, y: u32) -> u16 {
+        let stride = self.0.stride[plane.min(1)];
+        let base = self.0.data[plane].unwrap().as_ptr() as *const u8;
+        unsafe {
+            let row = base.offset(y as isize * stride);
+            if self.bit_depth() > 8 {
+                *(row as *const u16).add(x as usize)
+            } else {
+                u16::from(*row.add(x as usize))
+            }
+        }
+    }
+
+    /// Read the luma plane scaled to 8 bit, as used for alpha planes.
+    fn luma8(&self, x: u32, y: u32) -> u8 {
+        let max = (1u32 << self.bit_depth()) - 1;
+        let v = u32::from(self.sample(0, x, y));
+        let v = if self.full_range() {
+            v
+        } else {
+            let s = 1 << (self.bit_depth() - 8);
+            (v.saturating_sub(16 * s) * max / (219 * s)).min(max)
+        };
+        ((v * 255 + max / 2) / max) as u8
+    }
+
+    fn yuv_converter(&self) -> YuvConverter {
+        let seq_hdr = unsafe { self.0.seq_hdr.unwrap().as_ref() };
+        let (kr, kb) = match seq_hdr.mtrx {
+            DAV1D_MC_BT709 => (0.2126, 0.0722),
+            DAV1D_MC_FCC => (0.30, 0.11),
+            DAV1D_MC_SMPTE240 => (0.212, 0.087),
+            DAV1D_MC_BT2020_NCL | DAV1D_MC_BT2020_CL => (0.2627, 0.0593),
+            // BT.601 is also used for unspecified matrix coefficients
+            _ => (0.299, 0.114),
+        };
+
+        let bit_depth = self.bit_depth();
+        let max = ((1u32 << bit_depth) - 1) as f32;
+        let scale = (1u32 << (bit_depth - 8)) as f32;
+        let full_range = self.full_range();
+        let layout = self.0.p.layout;
+        YuvConverter {
+            kr,
+            kb,
+            identity: seq_hdr.mtrx == DAV1D_MC_IDENTITY,
+            monochrome: layout == DAV1D_PIXEL_LAYOUT_I400,
+            ss_x: u32::from(layout == DAV1D_PIXEL_LAYOUT_I420 || layout == DAV1D_PIXEL_LAYOUT_I422),
+            ss_y: u32::from(layout == DAV1D_PIXEL_LAYOUT_I420),
+            y_offset: if full_range { 0.0 } else { 16.0 * scale },
+            y_range: if full_range { max } else { 219.0 * scale },
+            uv_offset: (1u32 << (bit_depth - 1)) as f32,
+            uv_range: if full_range { max } else { 224.0 * scale },
+        }
+    }
+}
+
+impl Drop for Picture {
+    fn drop(&mut self) {
+        unsafe { dav1d_picture_unref(Some(NonNull::from(&mut self.0))) }
+    }
+}
+
+struct YuvConverter {
+    kr: f32,
+    kb: f32,
+    identity: bool,
+    monochrome: bool,
+    ss_x: u32,
+    ss_y: u32,
+    y_offset: f32,
+    y_range: f32,
+    uv_offset: f32,
+    uv_range: f32,
+}
+
+impl YuvConverter {
+    fn to_rgb(&self, picture: &Picture, x: u32, y: u32) -> [u8; 3] {
+        let luma = (f32::from(picture.sample(0, x, y)) - self.y_offset) / self.y_range;
+        if self.monochrome {
+            let v = to_u8(luma);
+            return [v; 3];
+        }
+
+        let (cx, cy) = (x >> self.ss_x, y >> self.ss_y);
+        if self.identity {
+            // GBR, stored as Y = G, U = B, V = R
+            let b = (f32::from(picture.sample(1, cx, cy)) - self.y_offset) / self.y_range;
+            let r = (f32::from(picture.sample(2, cx, cy)) - self.y_offset) / self.y_range;
+            return [to_u8(r), to_u8(luma), to_u8(b)];
+        }
+
+        let cb = (f32::from(picture.sample(1, cx, cy)) - self.uv_offset) / self.uv_range;
+        let cr = (f32::from(picture.sample(2, cx, cy)) - self.uv_offset) / self.uv_range;
+        let r = luma + (2.0 - 2.0 * self.kr) * cr;
+        let b = luma + (2.0 - 2.0 * self.kb) * cb;
+        let g = (luma - self.kr * r - self.kb * b) / (1.0 - self.kr - self.kb);
+        [to_u8(r), to_u8(g), to_u8(b)]
+    }
+}
+
+fn to_u8(v: f32) -> u8 {
+    (v.clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+}
+
+/// Embed `icc_profile` into an AVIF (as written by ravif), by adding a `colr` property of type
+/// `prof` and associating it with the primary item.
+///
+/// Returns `None` if the container doesn't have the expected structure.
+fn embed_icc_profile(avif: &[u8], icc_profile: &[u8]) -> Option<Vec<u8>> {
+    let mut patch = MetaPatch::new(avif)?;
+    let meta_content = patch.meta_content();
+    let primary_item = primary_item(avif, meta_content.clone())?;
+    let iprp = find_box(avif, meta_content, b"iprp")?;
+    let ipco = find_box(avif, iprp.start + 8..iprp.end, b"ipco")?;
+    let ipma = find_box(avif, iprp.start + 8..iprp.end, b"ipma")?;
+
+    let mut colr = Vec::with_capacity(12 + icc_profile.len());
+    colr.extend_from_slice(&u32::try_from(12 + icc_profile.len()).ok()?.to_be_bytes());
+    colr.extend_from_slice(b"colrprof");
+    colr.extend_from_slice(icc_profile);
+    // property indices are 1-based
+    let property_index = boxes(avif, ipco.start + 8..ipco.end).count() + 1;
+
+    // find the primary item's associations
+    let large_indices = avif.get(ipma.start + 11)? & 1 == 1;
+    let association = associations(avif, ipma.clone())?
+        .into_iter()
+        .find(|association| association.item == primary_item)?;
+    let count = avif[association.count_pos].checked_add(1)?;
+    let property = if large_indices {
+        u16::try_from(property_index)
+            .ok()
+            .filter(|i| *i < 0x8000)?
+            .to_be_bytes()
+            .to_vec()
+    } else {
+        vec![u8::try_from(property_index).ok().filter(|i| *i < 0x80)?]
+    };
+
+    patch.insert(ipco.end, colr, &[&iprp, &ipco]);
+    patch.insert(association.end, property, &[&iprp, &ipma]);
+    patch.replace(association.count_pos, &[count]);
+    patch.apply()
+}
+
+/// Embed the Exif data `exif` (a TIFF structure) and the XMP packet `xmp` into an AVIF (as written
+/// by ravif), by adding an `Exif` and a `mime` item that describe the primary item, with their data
+/// appended to `mdat`.
+///
+/// Returns `None` if the container doesn't have the expected structure.
+fn embed_metadata(avif: &[u8], exif: Option<&[u8]>, xmp: Option<&str>) -> Option<Vec<u8>> {
+    // the Exif payload is prefixed with the offset to its TIFF header
+    let mut items: Vec<(&[u8; 4], &[u8], Vec<u8>)> = Vec::new();
+    if let Some(exif) = exif {
+        items.push((b"Exif", b"", [&[0; 4], exif].concat()));
+    }
+    if let Some(xmp) = xmp {
+        items.push((b"mime", b"application/rdf+xml\0", xmp.as_bytes().to_vec()));
+    }
+    if items.is_empty() {
+        return Some(avif.to_vec());
+    }
+
+    let mut patch = MetaPatch::new(avif)?;
+    let meta_content = patch.meta_content();
+    let primary_item = primary_item(avif, meta_content.clone())?;
+    let iloc = patch.iloc.clone();
+    let iinf = find_box(avif, meta_content.clone(), b"iinf")?;
+    let iref = find_box(avif, meta_content, b"iref");
+
+    // new items get the IDs following the highest one in use
+    let iinf_version = *avif.get(iinf.start + 8)?;
+    let iinf_entries = iinf.start + if iinf_version == 0 { 14 } else { 16 };
+    let mut max_item = primary_item;
+    for (fourcc, infe) in boxes(avif, iinf_entries..iinf.end) {
+        let item = match avif.get(infe.start + 8)? {
+            _ if &fourcc != b"infe" => continue,
+            2 => u32::from(u16_at(avif, infe.start + 12)?),
+            3 => u32_at(avif, infe.start + 12)?,
+            _ => return None,
+        };
+        max_item = max_item.max(item);
+    }
+    let ids = (1..=items.len() as u32)
+        .map(|i| u16::try_from(max_item + i).ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    // item infos (version 2, i.e. with 16-bit item IDs)
+    let mut infes = Vec::new();
+    for (id, (item_type, content_type, _)) in ids.iter().zip(&items) {
+        infes.extend_from_slice(&(21 + content_type.len() as u32).to_be_bytes());
+        infes.extend_from_slice(b"infe\x02\0\0\0");
+        infes.extend_from_slice(&id.to_be_bytes());
+        infes.extend_from_slice(&[0, 0]); // protection index
+        infes.extend_from_slice(*item_type);
+        infes.push(0); // item name
+        infes.extend_from_slice(content_type);
+    }
+
+    // `cdsc` references, i.e. the new items describe the primary item
+    let iref_version = match &iref {
+        Some(iref) => *avif.get(iref.start + 8)?,
+        None => 0,
+    };
+    let mut refs = Vec::new();
+    for id in &ids {
+        let mut reference = Vec::new();
+        if iref_version == 0 {
+            reference.extend_from_slice(&id.to_be_bytes());
+            reference.extend_from_slice(&[0, 1]); // reference count
+            reference.extend_from_slice(&u16::try_from(primary_item).ok()?.to_be_bytes());
+        } else {
+            reference.extend_from_slice(&u32::from(*id).to_be_bytes());
+            reference.extend_from_slice(&[0, 1]); // reference count
+            reference.extend_from_slice(&primary_item.to_be_bytes());
+        }
+        refs.extend_from_slice(&(8 + reference.len() as u32).to_be_bytes());
+        refs.extend_from_slice(b"cdsc");
+        refs.extend_from_slice(&reference);
+    }
+
+    // item locations, as a single extent of the data appended to `mdat`
+    let iloc_version = *avif.get(iloc.start + 8)?;
+    let sizes = *avif.get(iloc.start + 12)?;
+    let (offset_size, length_size) = (usize::from(sizes >> 4), usize::from(sizes & 0xF));
+    let sizes = *avif.get(iloc.start + 13)?;
+    let base_offset_size = usize::from(sizes >> 4);
+    let index_size = if iloc_version == 1 {
+        usize::from(sizes & 0xF)
+    } else {
+        0
+    };
+    if iloc_version > 1 || ![4, 8].contains(&offset_size) || ![4, 8].contains(&length_size) {
+        return None;
+    }
+    let mut locs = Vec::new();
+    for (id, (_, _, data)) in ids.iter().zip(&items) {
+        let offset = patch.append(data) as u64;
+        locs.extend_from_slice(&id.to_be_bytes());
+        if iloc_version == 1 {
+            locs.extend_from_slice(&[0, 0]); // construction method
+        }
+        locs.extend_from_slice(&[0, 0]); // data reference index
+        locs.resize(locs.len() + base_offset_size, 0);
+        locs.extend_from_slice(&[0, 1]); // extent count
+        locs.resize(locs.len() + index_size, 0);
+        for (v, size) in [(offset, offset_size), (data.len() as u64, length_size)] {
+            match size {
+                4 => locs.extend_from_slice(&u32::try_from(v).ok()?.to_be_bytes()),
+                _ => locs.extend_from_slice(&v.to_be_bytes()),
+            }
+        }
+    }
+
+    // update the item counts
+    let item_count = u16_at(avif, iloc.start + 14)?.checked_add(items.len() as u16)?;
+    patch.replace(iloc.start + 14, &item_count.to_be_bytes());
+    if iinf_version == 0 {
+        let entry_count = u16_at(avif, iinf.start + 12)?.checked_add(items.len() as u16)?;
+        patch.replace(iinf.start + 12, &entry_count.to_be_bytes());
+    } else {
+        let entry_count = u32_at(avif, iinf.start + 12)?.checked_add(items.len() as u32)?;
+        patch.replace(iinf.start + 12, &entry_count.to_be_bytes());
+    }
+
+    patch.insert(iloc.end, locs, &[&iloc]);
+    patch.insert(iinf.end, infes, &[&iinf]);
+    match &iref {
+        Some(iref) => patch.insert(iref.end, refs, &[iref]),
+        // a new `iref` follows `iinf`
+        None => {
+            let header = [&(12 + refs.len() as u32).to_be_bytes()[..], b"iref\0\0\0\0"];
+            patch.insert(iinf.end, [&header.concat(), &refs[..]].concat(), &[]);
+        }
+    }
+    patch.apply()
+}
+
+/// Additions to an AVIF (as written by ravif): data inserted into `meta` and data appended to
+/// `mdat`. As `meta` precedes the image data, growing it shifts the file offsets in `iloc`, which
+/// [`MetaPatch::apply`] updates along with the sizes of the grown boxes.
+struct MetaPatch<'a> {
+    avif: &'a [u8],
+    meta: std::ops::Range<usize>,
+    mdat: std::ops::Range<usize>,
+    iloc: std::ops::Range<usize>,
+    /// Data to insert at a position, with the start of every box it grows besides `meta`
+    insertions: Vec<(usize, Vec<u8>, Vec<usize>)>,
+    /// Data to overwrite at a position, e.g. to update counts
+    replacements: Vec<(usize, Vec<u8>)>,
+    appended: Vec<u8>,
+}
+
+impl<'a> MetaPatch<'a> {
+    fn new(avif: &'a [u8]) -> Option<Self> {
+        let meta = find_box(avif, 0..avif.len(), b"meta")?;
+        let mdat = find_box(avif, 0..avif.len(), b"mdat")?;
+        if mdat.start < meta.end {
+            return None;
+        }
+        let iloc = find_box(avif, meta.start + 12..meta.end, b"iloc")?;
+        Some(Self {
+            avif,
+            meta,
+            mdat,
+            iloc,
+            insertions: Vec::new(),
+            replacements: Vec::new(),
+            appended: Vec::new(),
+        })
+    }
+
+    /// The content of `meta`, which is a full box, i.e. starts with version and flags.
+    fn meta_content(&self) -> std::ops::Range<usize> {
+        self.meta.start + 12..self.meta.end
+    }
+
+    /// Insert `data` at `pos` (within `meta`), growing `meta` and the `parents` boxes.
+    fn insert(&mut self, pos: usize, data: Vec<u8>, parents: &[&std::ops::Range<usize>]) {
+        let parents = parents.iter().map(|parent| parent.start).collect();
+        self.insertions.push((pos, data, parents));
+    }
+
+    /// Overwrite the data at `pos` (within `meta`).
+    fn replace(&mut self, pos: usize, data: &[u8]) {
+        self.replacements.push((pos, data.to_vec()));
+    }
+
+    /// Append `data` to `mdat`, and return its file offset. Like all offsets in `iloc`, it is
+    /// relative to the unpatched file, and shifted by [`MetaPatch::apply`].
+    fn append(&mut self, data: &[u8]) -> usize {
+        let offset = self.avif.len() + self.appended.len();
+        self.appended.extend_from_slice(data);
+        offset
+    }
+
+    /// Returns `None` if the container doesn't have the expected structure.
+    fn apply(mut self) -> Option<Vec<u8>> {
+        let avif = self.avif;
+        let delta: usize = self.insertions.iter().map(|(_, data, _)| data.len()).sum();
+        // appended data has to end up in `mdat`, which thus has to be the last box
+        if !self.appended.is_empty() && self.mdat.end != avif.len() {
+            return None;
+        }
+
+        let mut out = avif.to_vec();
+        for (pos, data) in &self.replacements {
+            out.get_mut(*pos..*pos + data.len())?.copy_from_slice(data);
+        }
+
+        // update the sizes of all boxes containing the new data
+        let mut grown = vec![
+            (self.meta.start, delta),
+            (self.mdat.start, self.appended.len()),
+        ];
+        for (_, data, parents) in &self.insertions {
+            grown.extend(parents.iter().map(|start| (*start, data.len())));
+        }
+        for (start, added) in grown {
+            let size = u32_at(&out, start)?.checked_add(u32::try_from(added).ok()?)?;
+            put_u32(&mut out, start, size)?;
+        }
+
+        // insert back to front, so that the positions stay valid, with insertions at the same
+        // position keeping their order
+        out.extend_from_slice(&self.appended);
+        self.insertions.sort_by_key(|(pos, _, _)| *pos);
+        for (pos, data, _) in self.insertions.into_iter().rev() {
+            out.splice(pos..pos, data);
+        }
+
+        // shift the file offsets of all items stored in `mdat`, including the appended ones
+        let meta = find_box(&out, 0..out.len(), b"meta")?;
+        let iloc = find_box(&out, meta.start + 12..meta.end, b"iloc")?;
+        for offset in iloc_offsets(&out, iloc)? {
+            match offset {
+                (pos, 4) => {
+                    let shifted = u32_at(&out, pos)?.checked_add(delta as u32)?;
+                    put_u32(&mut out, pos, shifted)?
+                }
+                (pos, 8) => {
+                    let shifted = u64_at(&out, pos)?.checked_add(delta as u64)?;
+                    put_u64(&mut out, pos, shifted)?
+                }
+                _ => return None,
+            }
+        }
+
+        Some(out)
+    }
+}
+
+/// The primary item, as referenced by `pitm` within `meta_content`.
+fn primary_item(avif: &[u8], meta_content: std::ops::Range<usize>) -> Option<u32> {
+    let pitm = find_box(avif, meta_content, b"pitm")?;
+    match *avif.get(pitm.start + 8)? {
+        0 => Some(u32::from(u16_at(avif, pitm.start + 12)?)),
+        _ => u32_at(avif, pitm.start + 12),
+    }
+}
+
+/// The properties associated with an item by an `ipma` entry.
+struct Association {
+    item: u32,
+    /// Position of the property count
+    count_pos: usize,
+    /// End of the entry
+    end: usize,
+    /// 1-based indices of the properties in `ipco`
+    properties: Vec<usize>,
+}
+
+/// Parse the entries of `ipma`.
+fn associations(data: &[u8], ipma: std::ops::Range<usize>) -> Option<Vec<Association>> {
+    let version = *data.get(ipma.start + 8)?;
+    let large_indices = data.get(ipma.start + 11)? & 1 == 1;
+    let entry_count = u32_at(data, ipma.start + 12)?;
+    let mut pos = ipma.start + 16;
+    let mut associations = Vec::new();
+    for _ in 0..entry_count {
+        let item = if version < 1 {
+            pos += 2;
+            u32::from(u16_at(data, pos - 2)?)
+        } else {
+            pos += 4;
+            u32_at(data, pos - 4)?
+        };
+        let count_pos = pos;
+        let count = *data.get(count_pos)?;
+        pos += 1;
+
+        // the highest bit of each index marks the property as essential
+        let mut properties = Vec::with_capacity(usize::from(count));
+        for _ in 0..count {
+            if large_indices {
+                properties.push(usize::from(u16_at(data, pos)? & 0x7FFF));
+                pos += 2;
+            } else {
+                properties.push(usize::from(*data.get(pos)? & 0x7F));
+                pos += 1;
+            }
+        }
+        associations.push(Association {
+            item,
+            count_pos,
+            end: pos,
+            properties,
+        });
+    }
+
+    (pos <= ipma.end).then_some(associations)
+}
+
+/// Positions and sizes of the absolute file offsets in `iloc`, that have to be updated when data
+/// is inserted in front of `mdat`.
+fn iloc_offsets(data: &[u8], iloc: std::ops::Range<usize>) -> Option<Vec<(usize, usize)>> {
+    let version = *data.get(iloc.start + 8)?;
+    let sizes = *data.get(iloc.start + 12)?;
+    let (offset_size, length_size) = (usize::from(sizes >> 4), usize::from(sizes & 0xF));
+    let sizes = *data.get(iloc.start + 13)?;
+    let base_offset_size = usize::from(sizes >> 4);
+    let index_size = if version > 0 {
+        usize::from(sizes & 0xF)
+    } else {
+        0
+    };
+
+    let mut pos = iloc.start + 14;
+    let item_count = if version < 2 {
+        pos += 2;
+        u32::from(u16_at(data, pos - 2)?)
+    } else {
+        pos += 4;
+        u32_at(data, pos - 4)?
+    };
+
+    let mut offsets = Vec::new();
+    for _ in 0..item_count {
+        pos += if version < 2 { 2 } else { 4 }; // item ID
+        let construction_method = if version > 0 {
+            pos += 2;
+            u16_at(data, pos - 2)? & 0xF
+        } else {
+            0
+        };
+        pos += 2; // data reference index
+        let base_offset_pos = pos;
+        pos += base_offset_size;
+        let extent_count = u16_at(data, pos)?;
+        pos += 2;
+
+        // only construction method 0 refers to file offsets (the others to `idat` or other items)
+        let file_offsets = construction_method == 0;
+        if file_offsets && base_offset_size > 0 {
+            offsets.push((base_offset_pos, base_offset_size));
+        }
+        for _ in 0..extent_count {
+            pos += index_size;
+            if file_offsets && base_offset_size == 0 {
+                offsets.push((pos, offset_size));
+            }
+            pos += offset_size + length_size;
+        }
+    }
+
+    (pos <= iloc.end).then_some(offsets)
+}
+
+/// Iterate the boxes within `range` of `data`, as their fourcc and range (including the header).
+fn boxes(
+    data: &[u8],
+    range: std::ops::Range<usize>,
+) -> impl Iterator<Item = ([u8; 4], std::ops::Range<usize>)> + '_ {
+    let end = range.end;
+    let mut pos = range.start;
+    std::iter::from_fn(move || {
+        // only 32-bit box sizes are supported, which is all ravif writes
+        let size = usize::try_from(u32_at(data, pos)?).ok()?;
+        let fourcc = data.get(pos + 4..pos + 8)?.try_into().ok()?;
+        let next = pos
+            .checked_add(size)
+            .filter(|next| size >= 8 && *next <= end)?;
+        let range = pos..next;
+        pos = next;
+        Some((fourcc, range))
+    })
+}
+
+fn find_box(
+    data: &[u8],
+    range: std::ops::Range<usize>,
+    fourcc: &[u8; 4],
+) -> Option<std::ops::Range<usize>> {
+    boxes(data, range).find_map(|(f, range)| (&f == fourcc).then_some(range))
+}
+
+fn u16_at(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+fn u32_at(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+fn u64_at(data: &[u8], pos: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(pos..pos + 8)?.try_into().ok()?))
+}
+
+fn put_u32(data: &mut [u8], pos: usize, v: u32) -> Option<()> {
+    data.get_mut(pos..pos + 4)?
+        .copy_from_slice(&v.to_be_bytes());
+    Some(())
+}
+
+fn put_u64(data: &mut [u8], pos: usize, v: u64) -> Option<()> {
+    data.get_mut(pos..pos + 8)?
+        .copy_from_slice(&v.to_be_bytes());
+    Some(())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AvifError {
+    #[error("failed to encode image as AVIF")]
+    Encode(#[from] ravif::Error),
+    #[error("failed to parse AVIF container")]
+    Parse(#[from] avif_parse::Error),
+    #[error("failed to decode AVIF image: {0}")]
+    Decode(&'static str),
+    #[error("failed to embed ICC profile into AVIF")]
+    EmbedIccProfile,
+    #[error("failed to embed metadata into AVIF")]
+    EmbedMetadata,
+}
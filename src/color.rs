@@ -0,0 +1,135 @@
+//! Color management, i.e. converting images from the color space described by their ICC profile
+//! to sRGB.
+
+use moxcms::{
+    curve_from_gamma, Chromaticity, CmsError, ColorPrimaries, ColorProfile, DataColorSpace, Layout,
+    Transform8BitExecutor, TransformOptions, XyY,
+};
+
+use crate::error::Error;
+use crate::{bytes16, samples16, Image, ImageFormat};
+
+/// Convert `img` from the color space of its ICC profile to sRGB. As images without a profile are
+/// assumed to be sRGB anyway, the result doesn't carry a profile, and images without one are
+/// returned unchanged.
+pub fn to_srgb(img: Image) -> Result<Image, Error> {
+    let Some(icc_profile) = img.icc_profile() else {
+        return Ok(img);
+    };
+
+    let layout = match img.format {
+        ImageFormat::RGB8 | ImageFormat::RGB16 => Layout::Rgb,
+        ImageFormat::RGBA8 | ImageFormat::RGBA16 => Layout::Rgba,
+        _ => {
+            return Err(Error::Process {
+                process: "convert to sRGB",
+                format: img.format,
+            })
+        }
+    };
+    let profile = ColorProfile::new_from_slice(icc_profile).map_err(ColorError::InvalidProfile)?;
+    let srgb = ColorProfile::new_srgb();
+    let opts = TransformOptions::default();
+
+    let data = match img.format {
+        ImageFormat::RGB8 | ImageFormat::RGBA8 => {
+            let transform = profile
+                .create_transform_8bit(layout, &srgb, layout, opts)
+                .map_err(ColorError::Transform)?;
+            let mut data = vec![0; img.as_ref().len()];
+            transform
+                .transform(img.as_ref(), &mut data)
+                .map_err(ColorError::Transform)?;
+            data
+        }
+        _ => {
+            let transform = profile
+                .create_transform_16bit(layout, &srgb, layout, opts)
+                .map_err(ColorError::Transform)?;
+            let samples = samples16(img.as_ref());
+            let mut data = vec![0; samples.len()];
+            transform
+                .transform(&samples, &mut data)
+                .map_err(ColorError::Transform)?;
+            bytes16(&data)
+        }
+    };
+
+    Ok(Image::new(data, img.format, img.width, img.height).with_metadata(img.metadata()))
+}
+
+/// A transform of 8-bit CMYK samples (with C, M, Y and K = 0 meaning no ink) to RGB8 in sRGB, or
+/// `None` if `icc_profile` isn't a CMYK profile.
+pub(crate) fn cmyk_to_srgb(
+    icc_profile: &[u8],
+) -> Result<Option<Box<Transform8BitExecutor>>, Error> {
+    let profile = ColorProfile::new_from_slice(icc_profile).map_err(ColorError::InvalidProfile)?;
+    if profile.color_space != DataColorSpace::Cmyk {
+        return Ok(None);
+    }
+
+    // 8-bit CMYK uses the same layout as RGBA
+    let transform = profile
+        .create_transform_8bit(
+            Layout::Rgba,
+            &ColorProfile::new_srgb(),
+            Layout::Rgb,
+            TransformOptions::default(),
+        )
+        .map_err(ColorError::Transform)?;
+    Ok(Some(transform))
+}
+
+/// Create an ICC profile from the white point and primaries (as `[white, red, green, blue]` xy
+/// chromaticities) and gamma of e.g. the PNG `cHRM` and `gAMA` chunks. If either is missing, the
+/// one of sRGB is used instead.
+pub(crate) fn icc_profile_from_chromaticities(
+    chromaticities: Option<[(f32, f32); 4]>,
+    gamma: Option<f32>,
+) -> Option<Vec<u8>> {
+    let mut profile = ColorProfile::new_srgb();
+    // the CICP tag would take precedence over the colorants and curves below
+    profile.cicp = None;
+    profile.description = None;
+
+    if let Some([white, red, green, blue]) = chromaticities {
+        if [white, red, green, blue].iter().any(|(_, y)| *y <= 0.0) {
+            return None;
+        }
+        let white = XyY {
+            x: f64::from(white.0),
+            y: f64::from(white.1),
+            yb: 1.0,
+        };
+        profile.update_rgb_colorimetry(
+            white,
+            ColorPrimaries {
+                red: Chromaticity::new(red.0, red.1),
+                green: Chromaticity::new(green.0, green.1),
+                blue: Chromaticity::new(blue.0, blue.1),
+            },
+        );
+        profile.media_white_point = Some(white.to_xyzd());
+    }
+
+    if let Some(gamma) = gamma {
+        if gamma <= 0.0 {
+            return None;
+        }
+        // PNG stores the encoding gamma, the curve however describes the decoding
+        let curve = curve_from_gamma(1.0 / gamma);
+        profile.red_trc = Some(curve.clone());
+        profile.green_trc = Some(curve.clone());
+        profile.blue_trc = Some(curve);
+    }
+
+    profile.encode().ok()
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ColorError {
+    #[error("invalid or unsupported ICC profile")]
+    InvalidProfile(#[source] CmsError),
+    #[error("failed to convert colors to sRGB")]
+    Transform(#[source] CmsError),
+}
@@ -1,20 +1,134 @@
 use crate::error::Error;
 use crate::{Image, ImageFormat, PixelFormat};
 
+/// Where to place the crop window within the image, e.g. `North` to keep the top edge.
 #[repr(u8)]
-pub enum Fit {
-    Contain = 0,
-    // Stretch = 1,
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Gravity {
+    #[default]
+    Center = 0,
+    North = 1,
+    NorthEast = 2,
+    East = 3,
+    SouthEast = 4,
+    South = 5,
+    SouthWest = 6,
+    West = 7,
+    NorthWest = 8,
+    /// Place the crop window on the most interesting region, judged by its edge density,
+    /// saturation and skin tones.
+    Attention = 9,
 }
 
-pub fn crop(img: &Image, width: u32, height: u32, fit: Fit) -> Result<Image, Error> {
-    if width == 0 || height == 0 || width > img.width || height > img.height {
-        return Err(Error::CropOutOfBounds);
+/// A point of interest, with `x` and `y` normalized to 0..1 (from the top left).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FocalPoint {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl TryFrom<u8> for Gravity {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => Gravity::Center,
+            1 => Gravity::North,
+            2 => Gravity::NorthEast,
+            3 => Gravity::East,
+            4 => Gravity::SouthEast,
+            5 => Gravity::South,
+            6 => Gravity::SouthWest,
+            7 => Gravity::West,
+            8 => Gravity::NorthWest,
+            9 => Gravity::Attention,
+            _ => {
+                return Err(Error::InvalidValue {
+                    name: "gravity",
+                    value,
+                })
+            }
+        })
+    }
+}
+
+impl Gravity {
+    /// The offset of a `width`x`height` crop window within the image.
+    fn offset(self, img: &Image, width: u32, height: u32) -> Result<(u32, u32), Error> {
+        let free_x = img.width.saturating_sub(width);
+        let free_y = img.height.saturating_sub(height);
+        let (center_x, center_y) = (free_x / 2, free_y / 2);
+        Ok(match self {
+            Gravity::Center => (center_x, center_y),
+            Gravity::North => (center_x, 0),
+            Gravity::NorthEast => (free_x, 0),
+            Gravity::East => (free_x, center_y),
+            Gravity::SouthEast => (free_x, free_y),
+            Gravity::South => (center_x, free_y),
+            Gravity::SouthWest => (0, free_y),
+            Gravity::West => (0, center_y),
+            Gravity::NorthWest => (0, 0),
+            Gravity::Attention => return crate::attention::offset(img, width, height),
+        })
+    }
+}
+
+impl FocalPoint {
+    /// The offset of a `width`x`height` crop window centered on the point, moved as little as
+    /// necessary to lie within the `image_width`x`image_height` image.
+    fn offset(self, image_width: u32, image_height: u32, width: u32, height: u32) -> (u32, u32) {
+        let axis = |point: f64, image_size: u32, size: u32| {
+            let free = image_size.saturating_sub(size);
+            let start = point * f64::from(image_size) - f64::from(size) / 2.0;
+            // NaN becomes 0
+            start.round().clamp(0.0, f64::from(free)) as u32
+        };
+        (
+            axis(self.x, image_width, width),
+            axis(self.y, image_height, height),
+        )
+    }
+}
+
+/// Crop the image to `width`x`height`, positioned according to `gravity`.
+pub fn crop(img: &Image, width: u32, height: u32, gravity: Gravity) -> Result<Image, Error> {
+    let (x, y) = gravity.offset(img, width, height)?;
+    crop_rect(img, x, y, width, height)
+}
+
+/// Crop the image to `width`x`height`, centered on `focal_point` as far as the image bounds allow.
+pub fn crop_around(
+    img: &Image,
+    width: u32,
+    height: u32,
+    focal_point: FocalPoint,
+) -> Result<Image, Error> {
+    let (x, y) = focal_point.offset(img.width, img.height, width, height);
+    crop_rect(img, x, y, width, height)
+}
+
+/// Crop the image to the `width`x`height` rectangle at `x`/`y` (from the top left). The rectangle
+/// must not be empty and must lie within the image.
+pub fn crop_rect(img: &Image, x: u32, y: u32, width: u32, height: u32) -> Result<Image, Error> {
+    let fits = |offset: u32, size: u32, bound: u32| {
+        size > 0 && offset.checked_add(size).is_some_and(|end| end <= bound)
+    };
+    if !fits(x, width, img.width) || !fits(y, height, img.height) {
+        return Err(Error::CropOutOfBounds {
+            x,
+            y,
+            width,
+            height,
+            image_width: img.width,
+            image_height: img.height,
+        });
     }
 
     let pixel_format = match img.format {
         ImageFormat::RGB8 => PixelFormat::RGB8,
         ImageFormat::RGBA8 => PixelFormat::RGBA8,
+        ImageFormat::RGB16 => PixelFormat::RGB16,
+        ImageFormat::RGBA16 => PixelFormat::RGBA16,
         _ => {
             return Err(Error::Process {
                 process: "crop",
@@ -23,19 +137,17 @@ pub fn crop(img: &Image, width: u32, height: u32, fit: Fit) -> Result<Image, Err
         }
     };
 
-    let (crop_x, crop_y) = match fit {
-        Fit::Contain => ((img.width - width) / 2, (img.height - height) / 2),
-        // Fit::Stretch => (0, 0),
-    };
-
     let src: &[u8] = img.as_ref();
     let mut dst = Vec::with_capacity((width * height) as usize * pixel_format.pixel_size());
 
-    for y in 0..height {
-        let from_start = ((y + crop_y) * img.width + crop_x) as usize * pixel_format.pixel_size();
+    for row in y..(y + height) {
+        let from_start =
+            (row as usize * img.width as usize + x as usize) * pixel_format.pixel_size();
         let from_end = from_start + (width as usize) * pixel_format.pixel_size();
         dst.extend_from_slice(&src[from_start..from_end]);
     }
 
-    Ok(Image::new(dst, img.format, width, height))
+    Ok(Image::new(dst, img.format, width, height)
+        .with_icc_profile(img.icc_profile())
+        .with_metadata(img.metadata()))
 }
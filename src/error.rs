@@ -9,13 +9,39 @@ pub enum Error {
     #[error("failed to process PNG image")]
     Png(#[from] crate::png::PngError),
     #[error("failed to process AVIF image")]
-    Avif(#[from] ravif::Error),
+    Avif(#[from] crate::avif::AvifError),
+    #[error("failed to encode ICO")]
+    Ico(#[from] crate::ico::IcoError),
+    #[error("failed to process GIF image")]
+    Gif(#[from] crate::gif::GifError),
+    #[error("failed to process JPEG XL image")]
+    Jxl(#[from] crate::jxl::JxlError),
+    #[error("failed to process TIFF image")]
+    Tiff(#[from] crate::tiff::TiffError),
+    #[error("failed to process QOI image")]
+    Qoi(#[from] crate::qoi::QoiError),
+    #[error("failed to process Netpbm image")]
+    Pnm(#[from] crate::pnm::PnmError),
+    #[cfg(feature = "color")]
+    #[error("failed to manage colors")]
+    Color(#[from] crate::color::ColorError),
     #[error("failed to process WEBP image: {0}")]
     Webp(&'static str),
-    #[error("crop out of bounds")]
-    CropOutOfBounds,
+    #[error(
+        "crop {width}x{height} at {x}/{y} out of bounds of {image_width}x{image_height} image"
+    )]
+    CropOutOfBounds {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        image_width: u32,
+        image_height: u32,
+    },
     #[error("received null pointer")]
     NullPtr,
+    #[error("invalid {name}: {value}")]
+    InvalidValue { name: &'static str, value: u8 },
     #[error("cannot {process} {format}")]
     Process {
         process: &'static str,
@@ -23,4 +49,8 @@ pub enum Error {
     },
     #[error("image exceeds available memory")]
     ExceedsMemory,
+    #[error("unknown image format")]
+    UnknownFormat,
+    #[error("converting to sRGB requires the `color` feature")]
+    ColorDisabled,
 }
@@ -0,0 +1,499 @@
+//! Bounds-checked parsing of JPEG marker segments and of the TIFF structure of Exif data. None of
+//! the functions in here panic, malformed input simply results in `None` or the end of iteration.
+//!
+//! - JPEG markers: https://www.w3.org/Graphics/JPEG/itu-t81.pdf (Annex B)
+//! - EXIF file format: https://www.media.mit.edu/pia/Research/deepview/exif.html
+
+/// The Exif orientation tag.
+pub const ORIENTATION: u16 = 0x0112;
+/// The Exif tag for a description of the image.
+pub const IMAGE_DESCRIPTION: u16 = 0x010e;
+/// The Exif tag for the manufacturer of the camera.
+pub const MAKE: u16 = 0x010f;
+/// The Exif tag for the model of the camera.
+pub const MODEL: u16 = 0x0110;
+/// The Exif tag for the creator of the image.
+pub const ARTIST: u16 = 0x013b;
+/// The Exif tag for the copyright notice.
+pub const COPYRIGHT: u16 = 0x8298;
+/// The Exif tag pointing to the Exif IFD, which holds e.g. the capture settings.
+pub const EXIF_IFD: u16 = 0x8769;
+/// The Exif tag pointing to the GPS IFD, which holds the location.
+pub const GPS_IFD: u16 = 0x8825;
+/// The Exif tag pointing to the Interoperability IFD (within the Exif IFD).
+pub const INTEROP_IFD: u16 = 0xa005;
+/// The Exif tag for the date and time the image was captured (within the Exif IFD).
+pub const DATE_TIME_ORIGINAL: u16 = 0x9003;
+
+/// A marker segment of a JPEG.
+#[derive(Debug, Clone, Copy)]
+pub struct Segment<'a> {
+    /// The marker, without its `0xFF` prefix (e.g. `0xE1` for APP1).
+    pub marker: u8,
+    /// The segment's payload, without marker and length.
+    pub data: &'a [u8],
+}
+
+/// Iterator over the marker segments of a JPEG, see [`segments`].
+#[derive(Debug, Clone)]
+pub struct Segments<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+/// Iterate the marker segments in front of the image data of a JPEG. Iteration ends at SOS (Start
+/// Of Scan), EOI (End Of Image) or at the first malformed segment.
+pub fn segments(jpeg: &[u8]) -> Segments<'_> {
+    Segments {
+        data: jpeg,
+        // skip SOI (Start Of Image) marker, or don't iterate anything if there is none
+        offset: if jpeg.starts_with(&[0xFF, 0xD8]) {
+            2
+        } else {
+            jpeg.len()
+        },
+    }
+}
+
+impl<'a> Iterator for Segments<'a> {
+    type Item = Segment<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let segment = self.read();
+        if segment.is_none() {
+            // stop for good
+            self.offset = self.data.len();
+        }
+        segment
+    }
+}
+
+impl<'a> Segments<'a> {
+    fn read(&mut self) -> Option<Segment<'a>> {
+        loop {
+            // markers are supposed to be prefixed with `0xFF`, if not, stop looking for segments
+            if *self.data.get(self.offset)? != 0xFF {
+                return None;
+            }
+
+            // any number of `0xFF` fill bytes may precede a marker
+            let mut offset = self.offset + 1;
+            while *self.data.get(offset)? == 0xFF {
+                offset += 1;
+            }
+            let marker = self.data[offset];
+            offset += 1;
+
+            match marker {
+                // SOS (Start Of Scan) and EOI (End Of Image)
+                0xDA | 0xD9 => return None,
+                // markers without a payload
+                0x01 | 0xD0..=0xD7 => {
+                    self.offset = offset;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let &[hi, lo] = self.data.get(offset..offset + 2)? else {
+                return None;
+            };
+            // the length includes the two length bytes themselves
+            let len = usize::from(u16::from_be_bytes([hi, lo])).checked_sub(2)?;
+            let data = self.data.get(offset + 2..offset + 2 + len)?;
+            self.offset = offset + 2 + len;
+
+            return Some(Segment { marker, data });
+        }
+    }
+}
+
+/// The TIFF structure of the Exif APP1 segment of a JPEG, without the `Exif\0\0` header.
+pub fn jpeg_exif(jpeg: &[u8]) -> Option<&[u8]> {
+    const HEADER: &[u8] = b"Exif\0\0";
+    segments(jpeg)
+        .find(|segment| segment.marker == 0xE1 && segment.data.starts_with(HEADER))
+        .map(|segment| &segment.data[HEADER.len()..])
+}
+
+/// The Exif orientation (1-8) of a JPEG, if it has any.
+pub fn orientation(jpeg: &[u8]) -> Option<u16> {
+    tiff_orientation(jpeg_exif(jpeg)?)
+}
+
+/// The orientation (1-8) of the Exif data `exif` (a TIFF structure), if it has any.
+pub fn tiff_orientation(exif: &[u8]) -> Option<u16> {
+    Tiff::parse(exif)?.ifd0()?.get(ORIENTATION)?.u16()
+}
+
+/// Set the orientation of the Exif data `exif` (a TIFF structure) to 1, i.e. upright, if it has
+/// one. Used once the orientation has been applied to the pixels.
+pub fn reset_orientation(exif: &mut [u8]) {
+    let Some(tiff) = Tiff::parse(exif) else {
+        return;
+    };
+    let Some(entry) = tiff
+        .ifd0()
+        .and_then(|ifd0| ifd0.get(ORIENTATION))
+        .filter(|entry| entry.u16().is_some())
+    else {
+        return;
+    };
+
+    let (offset, value) = (entry.value_offset, tiff.byte_order.u16_bytes(1));
+    exif[offset..offset + 2].copy_from_slice(&value);
+}
+
+/// Rewrite the Exif data `exif` (a TIFF structure), keeping only the entries of IFD0 and the Exif
+/// IFD whose tag is in `tags`. The GPS IFD is kept as a whole if [`GPS_IFD`] is in `tags`, while
+/// the Interoperability IFD and IFD1 (the thumbnail) are always dropped. Returns `None` if no
+/// entries are left.
+pub fn retain(exif: &[u8], tags: &[u16]) -> Option<Vec<u8>> {
+    let tiff = Tiff::parse(exif)?;
+    let ifd0 = tiff.ifd0()?;
+    let sub_ifd = |tag| {
+        ifd0.get(tag)
+            .and_then(|entry| entry.u32())
+            .and_then(|offset| tiff.ifd_at(offset))
+    };
+    // pointers to other IFDs are recreated below, as the offsets change
+    let fields = |ifd: Ifd<'_>, all: bool| {
+        ifd.entries()
+            .filter(|entry| !matches!(entry.tag, EXIF_IFD | GPS_IFD | INTEROP_IFD))
+            .filter(|entry| all || tags.contains(&entry.tag))
+            .map(|entry| Field {
+                tag: entry.tag,
+                field_type: entry.field_type,
+                count: entry.count,
+                value: entry.value.to_vec(),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut ifd0_fields = fields(ifd0, false);
+    let exif_fields = sub_ifd(EXIF_IFD)
+        .map(|ifd| fields(ifd, false))
+        .unwrap_or_default();
+    let gps_fields = sub_ifd(GPS_IFD)
+        .filter(|_| tags.contains(&GPS_IFD))
+        .map(|ifd| fields(ifd, true))
+        .unwrap_or_default();
+    if ifd0_fields.is_empty() && exif_fields.is_empty() && gps_fields.is_empty() {
+        return None;
+    }
+
+    // the sub IFDs follow IFD0 (and its values), which thereby grows by the pointers to them
+    let byte_order = tiff.byte_order;
+    let pointers = [(EXIF_IFD, &exif_fields), (GPS_IFD, &gps_fields)];
+    let pointer_count = pointers.iter().filter(|(_, f)| !f.is_empty()).count();
+    let mut offset = 8 + ifd_size(&ifd0_fields) + pointer_count * 12;
+    for (tag, sub_fields) in pointers {
+        if sub_fields.is_empty() {
+            continue;
+        }
+        ifd0_fields.push(Field {
+            tag,
+            field_type: 4, // LONG
+            count: 1,
+            value: byte_order.u32_bytes(u32::try_from(offset).ok()?).to_vec(),
+        });
+        offset += ifd_size(sub_fields);
+    }
+    ifd0_fields.sort_by_key(|field| field.tag);
+
+    let mut out = match byte_order {
+        ByteOrder::LittleEndian => b"II".to_vec(),
+        ByteOrder::BigEndian => b"MM".to_vec(),
+    };
+    out.extend_from_slice(&byte_order.u16_bytes(0x002a));
+    out.extend_from_slice(&byte_order.u32_bytes(8));
+    for fields in [&ifd0_fields, &exif_fields, &gps_fields] {
+        if !fields.is_empty() {
+            write_ifd(&mut out, byte_order, fields)?;
+        }
+    }
+
+    Some(out)
+}
+
+/// An IFD entry to be written by [`retain`].
+struct Field {
+    tag: u16,
+    field_type: u16,
+    count: u32,
+    /// The raw value, in the byte order of the TIFF structure.
+    value: Vec<u8>,
+}
+
+/// The size of an IFD including the values that don't fit into its entries.
+fn ifd_size(fields: &[Field]) -> usize {
+    let values: usize = fields
+        .iter()
+        .filter(|field| field.value.len() > 4)
+        // values are aligned to word boundaries
+        .map(|field| field.value.len().next_multiple_of(2))
+        .sum();
+    2 + fields.len() * 12 + 4 + values
+}
+
+/// Append an IFD (without a next IFD) and its values to `out`.
+fn write_ifd(out: &mut Vec<u8>, byte_order: ByteOrder, fields: &[Field]) -> Option<()> {
+    let mut value_offset = out.len() + 2 + fields.len() * 12 + 4;
+    out.extend_from_slice(&byte_order.u16_bytes(u16::try_from(fields.len()).ok()?));
+    for field in fields {
+        out.extend_from_slice(&byte_order.u16_bytes(field.tag));
+        out.extend_from_slice(&byte_order.u16_bytes(field.field_type));
+        out.extend_from_slice(&byte_order.u32_bytes(field.count));
+        if field.value.len() <= 4 {
+            // inline values are left-justified
+            let mut value = [0; 4];
+            value[..field.value.len()].copy_from_slice(&field.value);
+            out.extend_from_slice(&value);
+        } else {
+            out.extend_from_slice(&byte_order.u32_bytes(u32::try_from(value_offset).ok()?));
+            value_offset += field.value.len().next_multiple_of(2);
+        }
+    }
+    out.extend_from_slice(&[0; 4]);
+
+    for field in fields.iter().filter(|field| field.value.len() > 4) {
+        out.extend_from_slice(&field.value);
+        if field.value.len() % 2 == 1 {
+            out.push(0);
+        }
+    }
+
+    Some(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteOrder {
+    LittleEndian,
+    BigEndian,
+}
+
+impl ByteOrder {
+    fn u16_bytes(self, v: u16) -> [u8; 2] {
+        match self {
+            ByteOrder::LittleEndian => v.to_le_bytes(),
+            ByteOrder::BigEndian => v.to_be_bytes(),
+        }
+    }
+
+    fn u32_bytes(self, v: u32) -> [u8; 4] {
+        match self {
+            ByteOrder::LittleEndian => v.to_le_bytes(),
+            ByteOrder::BigEndian => v.to_be_bytes(),
+        }
+    }
+}
+
+/// A TIFF structure, as used for Exif data.
+#[derive(Debug, Clone, Copy)]
+pub struct Tiff<'a> {
+    data: &'a [u8],
+    byte_order: ByteOrder,
+    ifd0_offset: u32,
+}
+
+impl<'a> Tiff<'a> {
+    /// Parse the TIFF header of `data`.
+    pub fn parse(data: &'a [u8]) -> Option<Self> {
+        let byte_order = match data.get(0..2)? {
+            b"II" => ByteOrder::LittleEndian,
+            b"MM" => ByteOrder::BigEndian,
+            _ => return None,
+        };
+        let mut tiff = Tiff {
+            data,
+            byte_order,
+            ifd0_offset: 0,
+        };
+        if tiff.u16_at(2)? != 0x002a {
+            return None;
+        }
+        tiff.ifd0_offset = tiff.u32_at(4)?;
+
+        Some(tiff)
+    }
+
+    pub fn byte_order(&self) -> ByteOrder {
+        self.byte_order
+    }
+
+    /// The first IFD (image file directory).
+    pub fn ifd0(&self) -> Option<Ifd<'a>> {
+        self.ifd_at(self.ifd0_offset)
+    }
+
+    /// The IFD at `offset`, e.g. as referenced by the Exif or GPS IFD pointer tags.
+    pub fn ifd_at(&self, offset: u32) -> Option<Ifd<'a>> {
+        let offset = usize::try_from(offset).ok()?;
+        let count = self.u16_at(offset)?;
+        Some(Ifd {
+            tiff: *self,
+            offset,
+            count,
+        })
+    }
+
+    fn u16_at(&self, offset: usize) -> Option<u16> {
+        let bytes = self.data.get(offset..offset.checked_add(2)?)?;
+        Some(self.u16(bytes.try_into().ok()?))
+    }
+
+    fn u32_at(&self, offset: usize) -> Option<u32> {
+        let bytes = self.data.get(offset..offset.checked_add(4)?)?;
+        Some(self.u32(bytes.try_into().ok()?))
+    }
+
+    fn u16(&self, bytes: [u8; 2]) -> u16 {
+        match self.byte_order {
+            ByteOrder::LittleEndian => u16::from_le_bytes(bytes),
+            ByteOrder::BigEndian => u16::from_be_bytes(bytes),
+        }
+    }
+
+    fn u32(&self, bytes: [u8; 4]) -> u32 {
+        match self.byte_order {
+            ByteOrder::LittleEndian => u32::from_le_bytes(bytes),
+            ByteOrder::BigEndian => u32::from_be_bytes(bytes),
+        }
+    }
+}
+
+/// An IFD (image file directory) of a [`Tiff`].
+#[derive(Debug, Clone, Copy)]
+pub struct Ifd<'a> {
+    tiff: Tiff<'a>,
+    offset: usize,
+    count: u16,
+}
+
+impl<'a> Ifd<'a> {
+    /// Iterate the entries of the IFD. Iteration ends at the first truncated entry, and entries
+    /// with an unknown type or a value out of bounds are skipped.
+    pub fn entries(&self) -> impl Iterator<Item = Entry<'a>> + '_ {
+        (0..usize::from(self.count))
+            .map_while(|i| self.entry_offset(i))
+            .map_while(|offset| {
+                let entry = self.tiff.data.get(offset..offset.checked_add(12)?)?;
+                Some((offset, entry))
+            })
+            .filter_map(|(offset, entry)| self.entry(offset, entry))
+    }
+
+    /// The entry for `tag`, if present.
+    pub fn get(&self, tag: u16) -> Option<Entry<'a>> {
+        self.entries().find(|entry| entry.tag == tag)
+    }
+
+    /// The IFD following this one (e.g. IFD1, which holds the thumbnail). As this follows offsets
+    /// from the data, callers walking the chain have to guard against cycles themselves.
+    pub fn next_ifd(&self) -> Option<Ifd<'a>> {
+        let offset = self
+            .tiff
+            .u32_at(self.entry_offset(usize::from(self.count))?)?;
+        if offset == 0 {
+            return None;
+        }
+        self.tiff.ifd_at(offset)
+    }
+
+    fn entry_offset(&self, i: usize) -> Option<usize> {
+        self.offset.checked_add(2)?.checked_add(i * 12)
+    }
+
+    fn entry(&self, offset: usize, entry: &'a [u8]) -> Option<Entry<'a>> {
+        let tiff = &self.tiff;
+        let tag = tiff.u16([entry[0], entry[1]]);
+        let field_type = tiff.u16([entry[2], entry[3]]);
+        let count = tiff.u32([entry[4], entry[5], entry[6], entry[7]]);
+
+        let size = usize::try_from(count)
+            .ok()?
+            .checked_mul(type_size(field_type)?)?;
+        let value_offset = if size <= 4 {
+            // values of up to 4 bytes are stored inline
+            offset + 8
+        } else {
+            let offset = tiff.u32([entry[8], entry[9], entry[10], entry[11]]);
+            usize::try_from(offset).ok()?
+        };
+        let value = tiff
+            .data
+            .get(value_offset..value_offset.checked_add(size)?)?;
+
+        Some(Entry {
+            tag,
+            field_type,
+            count,
+            value,
+            value_offset,
+            byte_order: tiff.byte_order,
+        })
+    }
+}
+
+/// An entry of an [`Ifd`].
+#[derive(Debug, Clone, Copy)]
+pub struct Entry<'a> {
+    pub tag: u16,
+    /// The TIFF field type, e.g. 3 for SHORT.
+    pub field_type: u16,
+    /// The number of values (not bytes).
+    pub count: u32,
+    value: &'a [u8],
+    /// The offset of the value within the TIFF structure.
+    value_offset: usize,
+    byte_order: ByteOrder,
+}
+
+impl<'a> Entry<'a> {
+    /// The raw bytes of the value(s), in the byte order of the TIFF.
+    pub fn value(&self) -> &'a [u8] {
+        self.value
+    }
+
+    /// The first value of a SHORT entry.
+    pub fn u16(&self) -> Option<u16> {
+        if self.field_type != 3 {
+            return None;
+        }
+        let bytes = self.value.get(..2)?.try_into().ok()?;
+        Some(match self.byte_order {
+            ByteOrder::LittleEndian => u16::from_le_bytes(bytes),
+            ByteOrder::BigEndian => u16::from_be_bytes(bytes),
+        })
+    }
+
+    /// The first value of a SHORT or LONG entry.
+    pub fn u32(&self) -> Option<u32> {
+        if self.field_type == 3 {
+            return self.u16().map(u32::from);
+        }
+        if self.field_type != 4 {
+            return None;
+        }
+        let bytes = self.value.get(..4)?.try_into().ok()?;
+        Some(match self.byte_order {
+            ByteOrder::LittleEndian => u32::from_le_bytes(bytes),
+            ByteOrder::BigEndian => u32::from_be_bytes(bytes),
+        })
+    }
+}
+
+/// The size in bytes of a single value of the TIFF field type.
+fn type_size(field_type: u16) -> Option<usize> {
+    match field_type {
+        // BYTE, ASCII, SBYTE, UNDEFINED
+        1 | 2 | 6 | 7 => Some(1),
+        // SHORT, SSHORT
+        3 | 8 => Some(2),
+        // LONG, SLONG, FLOAT, IFD
+        4 | 9 | 11 | 13 => Some(4),
+        // RATIONAL, SRATIONAL, DOUBLE
+        5 | 10 | 12 => Some(8),
+        _ => None,
+    }
+}
@@ -1,8 +1,9 @@
 use std::ffi::CString;
 use std::os::raw::c_char;
 
+use crate::animation::Animation;
 use crate::error::Error;
-use crate::Image;
+use crate::{Image, ImageFormat, ImageInfo};
 
 macro_rules! as_mut {
     ($expr:expr $(,)?) => {
@@ -17,9 +18,15 @@ macro_rules! as_mut {
 #[derive(Default)]
 pub struct Context {
     last_error: Option<Error>,
+    jpeg_decode_options: crate::jpeg::DecodeOptions,
     jpeg_encode_options: crate::jpeg::EncodeOptions,
+    png_decode_options: crate::png::DecodeOptions,
     avif_encode_options: crate::avif::EncodeOptions,
     webp_encode_options: crate::webp::EncodeOptions,
+    jxl_encode_options: crate::jxl::EncodeOptions,
+    tiff_decode_options: crate::tiff::DecodeOptions,
+    pnm_decode_options: crate::pnm::DecodeOptions,
+    resize_options: crate::resize::ResizeOptions,
 }
 
 #[no_mangle]
@@ -103,6 +110,59 @@ pub unsafe extern "C" fn image_drop(img: *mut Image) {
     let _ = Box::from_raw(img);
 }
 
+/// Drop all Exif and XMP metadata of `img`, except for the `len` Exif tags at `tags` (e.g. `0x8298`
+/// for the copyright notice, or `0x8825` for the GPS data) and the XMP packet if `keep_xmp` is set.
+/// Pass no tags and `false` to strip all metadata.
+#[no_mangle]
+pub unsafe extern "C" fn image_retain_metadata(
+    img: *mut Image,
+    tags: *const u16,
+    len: usize,
+    keep_xmp: bool,
+) -> i32 {
+    let img: &mut Image = as_mut!(img);
+    img.retain_metadata(&allowlist(tags, len, keep_xmp));
+    0
+}
+
+/// The metadata to keep, given as the `len` Exif tags at `tags` (which might be null) and whether
+/// to keep the XMP packet.
+unsafe fn allowlist(tags: *const u16, len: usize, keep_xmp: bool) -> crate::metadata::Allowlist {
+    let exif_tags = if tags.is_null() {
+        Vec::new()
+    } else {
+        std::slice::from_raw_parts(tags, len).to_vec()
+    };
+    crate::metadata::Allowlist {
+        exif_tags,
+        xmp: keep_xmp,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn animation_new(width: u32, height: u32, loop_count: u32) -> *mut Animation {
+    Box::into_raw(Box::new(Animation::new(width, height, loop_count)))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn animation_drop(anim: *mut Animation) {
+    let _ = Box::from_raw(anim);
+}
+
+/// Append a copy of `img` as a new frame, displayed for `delay` milliseconds.
+#[no_mangle]
+pub unsafe extern "C" fn animation_push_frame(
+    anim: *mut Animation,
+    img: *mut Image,
+    delay: u32,
+) -> i32 {
+    let anim: &mut Animation = as_mut!(anim);
+    let img: &mut Image = as_mut!(img);
+
+    anim.push(img.clone(), delay);
+    0
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn resize(
     ctx: *mut Context,
@@ -116,7 +176,11 @@ pub unsafe extern "C" fn resize(
     let img: &mut Image = as_mut!(img);
     let out: &mut Image = as_mut!(out);
 
-    match crate::resize::resize(img, new_width, new_height, maintain_aspect) {
+    let opts = crate::resize::ResizeOptions {
+        maintain_aspect,
+        ..ctx.resize_options.clone()
+    };
+    match crate::resize::resize_with_options(img, new_width, new_height, &opts) {
         Ok(img) => {
             *out = img;
             0
@@ -128,6 +192,185 @@ pub unsafe extern "C" fn resize(
     }
 }
 
+/// Set where to crop images whose aspect ratio is changed by `resize` (see `Gravity`). Unsets the
+/// focal point. Invalid values are ignored.
+#[no_mangle]
+pub unsafe extern "C" fn resize_set_gravity(ctx: *mut Context, gravity: u8) {
+    if let Some(ctx) = ctx.as_mut() {
+        if let Ok(gravity) = crate::crop::Gravity::try_from(gravity) {
+            ctx.resize_options.gravity = gravity;
+            ctx.resize_options.focal_point = None;
+        }
+    }
+}
+
+/// Set the point (`x` and `y` normalized to 0..1) to keep in the center of images cropped by
+/// `resize`. Takes precedence over the gravity, until `resize_set_gravity` is called.
+#[no_mangle]
+pub unsafe extern "C" fn resize_set_focal_point(ctx: *mut Context, x: f64, y: f64) {
+    if let Some(ctx) = ctx.as_mut() {
+        ctx.resize_options.focal_point = Some(crate::crop::FocalPoint { x, y });
+    }
+}
+
+/// Set the resampling filter used by `resize` (see `Filter`, defaults to `Triangle`). Invalid
+/// values are ignored.
+#[no_mangle]
+pub unsafe extern "C" fn resize_set_filter(ctx: *mut Context, filter: u8) {
+    if let Some(ctx) = ctx.as_mut() {
+        if let Ok(filter) = crate::resize::Filter::try_from(filter) {
+            ctx.resize_options.filter = filter;
+        }
+    }
+}
+
+/// Crop the image to the `width`x`height` rectangle at `x`/`y` (from the top left).
+#[no_mangle]
+pub unsafe extern "C" fn crop(
+    ctx: *mut Context,
+    img: *mut Image,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    out: *mut Image,
+) -> i32 {
+    let ctx: &mut Context = as_mut!(ctx);
+    let img: &mut Image = as_mut!(img);
+    let out: &mut Image = as_mut!(out);
+
+    match crate::crop::crop_rect(img, x, y, width, height) {
+        Ok(img) => {
+            *out = img;
+            0
+        }
+        Err(err) => {
+            ctx.last_error = Some(err);
+            ErrorCode::Crop as i32
+        }
+    }
+}
+
+/// Rotate or flip the image, as given by `transform` (see `Transform`).
+#[no_mangle]
+pub unsafe extern "C" fn transform(
+    ctx: *mut Context,
+    img: *mut Image,
+    transform: u8,
+    out: *mut Image,
+) -> i32 {
+    let ctx: &mut Context = as_mut!(ctx);
+    let img: &mut Image = as_mut!(img);
+    let out: &mut Image = as_mut!(out);
+
+    let result = crate::transform::Transform::try_from(transform)
+        .and_then(|transform| crate::transform::transform(img, transform));
+    match result {
+        Ok(img) => {
+            *out = img;
+            0
+        }
+        Err(err) => {
+            ctx.last_error = Some(err);
+            ErrorCode::Transform as i32
+        }
+    }
+}
+
+/// Convert the image from the color space of its ICC profile to sRGB.
+#[cfg(feature = "color")]
+#[no_mangle]
+pub unsafe extern "C" fn to_srgb(ctx: *mut Context, img: *mut Image, out: *mut Image) -> i32 {
+    let ctx: &mut Context = as_mut!(ctx);
+    let img: &mut Image = as_mut!(img);
+    let out: &mut Image = as_mut!(out);
+
+    match crate::color::to_srgb(img.clone()) {
+        Ok(img) => {
+            *out = img;
+            0
+        }
+        Err(err) => {
+            ctx.last_error = Some(err);
+            ErrorCode::Color as i32
+        }
+    }
+}
+
+/// Detect the format of an encoded image. Returns `0` if the format is unknown.
+#[no_mangle]
+pub unsafe extern "C" fn detect_format(ptr: *const u8, size: usize) -> u8 {
+    if ptr.is_null() {
+        return 0;
+    }
+
+    let data = std::slice::from_raw_parts(ptr, size);
+    crate::detect_format(data).map_or(0, |format| format as u8)
+}
+
+/// Decode an image of any supported format, detected from its magic bytes.
+#[no_mangle]
+pub unsafe extern "C" fn decode(
+    ctx: *mut Context,
+    ptr: *const u8,
+    size: usize,
+    out: *mut Image,
+) -> i32 {
+    let ctx: &mut Context = as_mut!(ctx);
+    let out: &mut Image = as_mut!(out);
+    if ptr.is_null() {
+        return ErrorCode::NullPtr as i32;
+    }
+
+    let data = std::slice::from_raw_parts(ptr, size);
+    let result = match crate::detect_format(data) {
+        Some(ImageFormat::JPEG) => crate::jpeg::decode_with_options(data, &ctx.jpeg_decode_options),
+        Some(ImageFormat::PNG) => crate::png::decode_with_options(data, &ctx.png_decode_options),
+        Some(ImageFormat::TIFF) => crate::tiff::decode_with_options(data, &ctx.tiff_decode_options),
+        Some(ImageFormat::PPM | ImageFormat::PAM) => {
+            crate::pnm::decode_with_options(data, &ctx.pnm_decode_options)
+        }
+        _ => crate::decode(data),
+    };
+    match result {
+        Ok(img) => {
+            *out = img;
+            0
+        }
+        Err(err) => {
+            ctx.last_error = Some(err);
+            ErrorCode::Decode as i32
+        }
+    }
+}
+
+/// Read the basic properties of an image of any supported format into `out`, without decoding it.
+#[no_mangle]
+pub unsafe extern "C" fn probe(
+    ctx: *mut Context,
+    ptr: *const u8,
+    size: usize,
+    out: *mut ImageInfo,
+) -> i32 {
+    let ctx: &mut Context = as_mut!(ctx);
+    let out: &mut ImageInfo = as_mut!(out);
+    if ptr.is_null() {
+        return ErrorCode::NullPtr as i32;
+    }
+
+    let data = std::slice::from_raw_parts(ptr, size);
+    match crate::probe(data) {
+        Ok(info) => {
+            *out = info;
+            0
+        }
+        Err(err) => {
+            ctx.last_error = Some(err);
+            ErrorCode::Decode as i32
+        }
+    }
+}
+
 #[cfg(not(target_family = "wasm"))]
 #[no_mangle]
 pub unsafe extern "C" fn hash(ptr: *mut u8, size: usize, seed: u32) -> u64 {
@@ -167,7 +410,7 @@ pub unsafe extern "C" fn jpeg_decode(
     }
 
     let data = std::slice::from_raw_parts(ptr, size);
-    match crate::jpeg::decode(data) {
+    match crate::jpeg::decode_with_options(data, &ctx.jpeg_decode_options) {
         Ok(img) => {
             *out = img;
             0
@@ -204,6 +447,28 @@ pub unsafe extern "C" fn jpeg_set_encode_quality(ctx: *mut Context, quality: u16
     }
 }
 
+#[cfg(feature = "color")]
+#[no_mangle]
+pub unsafe extern "C" fn jpeg_set_decode_to_srgb(ctx: *mut Context, to_srgb: bool) {
+    if let Some(ctx) = ctx.as_mut() {
+        ctx.jpeg_decode_options.to_srgb = to_srgb;
+    }
+}
+
+/// Keep the `len` Exif tags at `tags` and the XMP packet (if `keep_xmp` is set) when decoding
+/// JPEGs. All other metadata is dropped, by default all of it.
+#[no_mangle]
+pub unsafe extern "C" fn jpeg_set_decode_keep_metadata(
+    ctx: *mut Context,
+    tags: *const u16,
+    len: usize,
+    keep_xmp: bool,
+) {
+    if let Some(ctx) = ctx.as_mut() {
+        ctx.jpeg_decode_options.keep_metadata = allowlist(tags, len, keep_xmp);
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn png_seed() -> u32 {
     crate::png::seed()
@@ -223,7 +488,7 @@ pub unsafe extern "C" fn png_decode(
     }
 
     let data = std::slice::from_raw_parts(ptr, size);
-    match crate::png::decode(data) {
+    match crate::png::decode_with_options(data, &ctx.png_decode_options) {
         Ok(img) => {
             *out = img;
             0
@@ -253,11 +518,66 @@ pub unsafe extern "C" fn png_encode(ctx: *mut Context, img: *mut Image, out: *mu
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn png_set_decode_keep_16bit(ctx: *mut Context, keep_16bit: bool) {
+    if let Some(ctx) = ctx.as_mut() {
+        ctx.png_decode_options.keep_16bit = keep_16bit;
+    }
+}
+
+#[cfg(feature = "color")]
+#[no_mangle]
+pub unsafe extern "C" fn png_set_decode_to_srgb(ctx: *mut Context, to_srgb: bool) {
+    if let Some(ctx) = ctx.as_mut() {
+        ctx.png_decode_options.to_srgb = to_srgb;
+    }
+}
+
+/// Keep the `len` Exif tags at `tags` and the XMP packet (if `keep_xmp` is set) when decoding
+/// PNGs. All other metadata is dropped, by default all of it.
+#[no_mangle]
+pub unsafe extern "C" fn png_set_decode_keep_metadata(
+    ctx: *mut Context,
+    tags: *const u16,
+    len: usize,
+    keep_xmp: bool,
+) {
+    if let Some(ctx) = ctx.as_mut() {
+        ctx.png_decode_options.keep_metadata = allowlist(tags, len, keep_xmp);
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn avif_seed() -> u32 {
     crate::avif::seed()
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn avif_decode(
+    ctx: *mut Context,
+    ptr: *const u8,
+    size: usize,
+    out: *mut Image,
+) -> i32 {
+    let ctx: &mut Context = as_mut!(ctx);
+    let out: &mut Image = as_mut!(out);
+    if ptr.is_null() {
+        return ErrorCode::NullPtr as i32;
+    }
+
+    let data = std::slice::from_raw_parts(ptr, size);
+    match crate::avif::decode(data) {
+        Ok(img) => {
+            *out = img;
+            0
+        }
+        Err(err) => {
+            ctx.last_error = Some(err);
+            ErrorCode::Decode as i32
+        }
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn avif_encode(ctx: *mut Context, img: *mut Image, out: *mut Image) -> i32 {
     let ctx: &mut Context = as_mut!(ctx);
@@ -295,6 +615,32 @@ pub unsafe extern "C" fn webp_seed() -> u32 {
     crate::webp::seed()
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn webp_decode(
+    ctx: *mut Context,
+    ptr: *const u8,
+    size: usize,
+    out: *mut Image,
+) -> i32 {
+    let ctx: &mut Context = as_mut!(ctx);
+    let out: &mut Image = as_mut!(out);
+    if ptr.is_null() {
+        return ErrorCode::NullPtr as i32;
+    }
+
+    let data = std::slice::from_raw_parts(ptr, size);
+    match crate::webp::decode(data) {
+        Ok(img) => {
+            *out = img;
+            0
+        }
+        Err(err) => {
+            ctx.last_error = Some(err);
+            ErrorCode::Decode as i32
+        }
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn webp_encode(ctx: *mut Context, img: *mut Image, out: *mut Image) -> i32 {
     let ctx: &mut Context = as_mut!(ctx);
@@ -313,6 +659,28 @@ pub unsafe extern "C" fn webp_encode(ctx: *mut Context, img: *mut Image, out: *m
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn webp_encode_animation(
+    ctx: *mut Context,
+    anim: *mut Animation,
+    out: *mut Image,
+) -> i32 {
+    let ctx: &mut Context = as_mut!(ctx);
+    let anim: &mut Animation = as_mut!(anim);
+    let out: &mut Image = as_mut!(out);
+
+    match crate::webp::encode_animation(anim, &ctx.webp_encode_options) {
+        Ok(img) => {
+            *out = img;
+            0
+        }
+        Err(err) => {
+            ctx.last_error = Some(err);
+            ErrorCode::Encode as i32
+        }
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn webp_set_encode_quality(ctx: *mut Context, quality: u16) {
     if let Some(ctx) = ctx.as_mut() {
@@ -320,17 +688,435 @@ pub unsafe extern "C" fn webp_set_encode_quality(ctx: *mut Context, quality: u16
     }
 }
 
-#[repr(i32)]
-pub enum ErrorCode {
-    /// Received an unexpected null pointer.
-    NullPtr = -1,
+#[no_mangle]
+pub unsafe extern "C" fn webp_set_encode_lossless(ctx: *mut Context, lossless: bool) {
+    if let Some(ctx) = ctx.as_mut() {
+        ctx.webp_encode_options.lossless = lossless;
+    }
+}
 
-    /// Failed to decode image.
-    Decode = -2,
+#[no_mangle]
+pub unsafe extern "C" fn webp_set_encode_near_lossless(ctx: *mut Context, near_lossless: u8) {
+    if let Some(ctx) = ctx.as_mut() {
+        ctx.webp_encode_options.near_lossless = near_lossless;
+    }
+}
 
-    /// Failed to encode image.
+#[no_mangle]
+pub unsafe extern "C" fn webp_set_encode_exact(ctx: *mut Context, exact: bool) {
+    if let Some(ctx) = ctx.as_mut() {
+        ctx.webp_encode_options.exact = exact;
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn webp_set_encode_method(ctx: *mut Context, method: u8) {
+    if let Some(ctx) = ctx.as_mut() {
+        ctx.webp_encode_options.method = method;
+    }
+}
+
+/// Set the preset (see `Preset`) to base the WebP encoding settings on. Invalid values are
+/// ignored.
+#[no_mangle]
+pub unsafe extern "C" fn webp_set_encode_preset(ctx: *mut Context, preset: u8) {
+    if let Some(ctx) = ctx.as_mut() {
+        if let Ok(preset) = crate::webp::Preset::try_from(preset) {
+            ctx.webp_encode_options.preset = preset;
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn webp_set_encode_alpha_quality(ctx: *mut Context, alpha_quality: u16) {
+    if let Some(ctx) = ctx.as_mut() {
+        ctx.webp_encode_options.alpha_quality = alpha_quality;
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn webp_set_encode_use_sharp_yuv(ctx: *mut Context, use_sharp_yuv: bool) {
+    if let Some(ctx) = ctx.as_mut() {
+        ctx.webp_encode_options.use_sharp_yuv = use_sharp_yuv;
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn gif_decode(
+    ctx: *mut Context,
+    ptr: *const u8,
+    size: usize,
+    out: *mut Image,
+) -> i32 {
+    let ctx: &mut Context = as_mut!(ctx);
+    let out: &mut Image = as_mut!(out);
+    if ptr.is_null() {
+        return ErrorCode::NullPtr as i32;
+    }
+
+    let data = std::slice::from_raw_parts(ptr, size);
+    match crate::gif::decode(data) {
+        Ok(img) => {
+            *out = img;
+            0
+        }
+        Err(err) => {
+            ctx.last_error = Some(err);
+            ErrorCode::Decode as i32
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn gif_decode_animation(
+    ctx: *mut Context,
+    ptr: *const u8,
+    size: usize,
+    out: *mut Animation,
+) -> i32 {
+    let ctx: &mut Context = as_mut!(ctx);
+    let out: &mut Animation = as_mut!(out);
+    if ptr.is_null() {
+        return ErrorCode::NullPtr as i32;
+    }
+
+    let data = std::slice::from_raw_parts(ptr, size);
+    match crate::gif::decode_animation(data) {
+        Ok(anim) => {
+            *out = anim;
+            0
+        }
+        Err(err) => {
+            ctx.last_error = Some(err);
+            ErrorCode::Decode as i32
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn jxl_seed() -> u32 {
+    crate::jxl::seed()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn jxl_decode(
+    ctx: *mut Context,
+    ptr: *const u8,
+    size: usize,
+    out: *mut Image,
+) -> i32 {
+    let ctx: &mut Context = as_mut!(ctx);
+    let out: &mut Image = as_mut!(out);
+    if ptr.is_null() {
+        return ErrorCode::NullPtr as i32;
+    }
+
+    let data = std::slice::from_raw_parts(ptr, size);
+    match crate::jxl::decode(data) {
+        Ok(img) => {
+            *out = img;
+            0
+        }
+        Err(err) => {
+            ctx.last_error = Some(err);
+            ErrorCode::Decode as i32
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn jxl_encode(ctx: *mut Context, img: *mut Image, out: *mut Image) -> i32 {
+    let ctx: &mut Context = as_mut!(ctx);
+    let img: &mut Image = as_mut!(img);
+    let out: &mut Image = as_mut!(out);
+
+    match crate::jxl::encode(img, &ctx.jxl_encode_options) {
+        Ok(img) => {
+            *out = img;
+            0
+        }
+        Err(err) => {
+            ctx.last_error = Some(err);
+            ErrorCode::Encode as i32
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn jxl_set_encode_quality(ctx: *mut Context, quality: u16) {
+    if let Some(ctx) = ctx.as_mut() {
+        ctx.jxl_encode_options.quality = quality;
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn jxl_set_encode_effort(ctx: *mut Context, effort: u8) {
+    if let Some(ctx) = ctx.as_mut() {
+        ctx.jxl_encode_options.effort = effort;
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn jxl_set_encode_lossless(ctx: *mut Context, lossless: bool) {
+    if let Some(ctx) = ctx.as_mut() {
+        ctx.jxl_encode_options.lossless = lossless;
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn jxl_set_encode_threads(ctx: *mut Context, threads: u8) {
+    if let Some(ctx) = ctx.as_mut() {
+        ctx.jxl_encode_options.threads = threads;
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tiff_decode(
+    ctx: *mut Context,
+    ptr: *const u8,
+    size: usize,
+    out: *mut Image,
+) -> i32 {
+    let ctx: &mut Context = as_mut!(ctx);
+    let out: &mut Image = as_mut!(out);
+    if ptr.is_null() {
+        return ErrorCode::NullPtr as i32;
+    }
+
+    let data = std::slice::from_raw_parts(ptr, size);
+    match crate::tiff::decode_with_options(data, &ctx.tiff_decode_options) {
+        Ok(img) => {
+            *out = img;
+            0
+        }
+        Err(err) => {
+            ctx.last_error = Some(err);
+            ErrorCode::Decode as i32
+        }
+    }
+}
+
+/// Returns the number of pages of the TIFF, or a negative error code.
+#[no_mangle]
+pub unsafe extern "C" fn tiff_page_count(ctx: *mut Context, ptr: *const u8, size: usize) -> i32 {
+    let ctx: &mut Context = as_mut!(ctx);
+    if ptr.is_null() {
+        return ErrorCode::NullPtr as i32;
+    }
+
+    let data = std::slice::from_raw_parts(ptr, size);
+    match crate::tiff::pages(data) {
+        Ok(pages) => pages.len() as i32,
+        Err(err) => {
+            ctx.last_error = Some(err);
+            ErrorCode::Decode as i32
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tiff_set_decode_page(ctx: *mut Context, page: u32) {
+    if let Some(ctx) = ctx.as_mut() {
+        ctx.tiff_decode_options.page = page as usize;
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tiff_set_decode_keep_16bit(ctx: *mut Context, keep_16bit: bool) {
+    if let Some(ctx) = ctx.as_mut() {
+        ctx.tiff_decode_options.keep_16bit = keep_16bit;
+    }
+}
+
+#[cfg(feature = "color")]
+#[no_mangle]
+pub unsafe extern "C" fn tiff_set_decode_to_srgb(ctx: *mut Context, to_srgb: bool) {
+    if let Some(ctx) = ctx.as_mut() {
+        ctx.tiff_decode_options.to_srgb = to_srgb;
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ico_seed() -> u32 {
+    crate::ico::seed()
+}
+
+/// Encode the `len` images pointed to by `imgs` into a single multi-resolution ICO.
+#[no_mangle]
+pub unsafe extern "C" fn ico_encode(
+    ctx: *mut Context,
+    imgs: *const *mut Image,
+    len: usize,
+    out: *mut Image,
+) -> i32 {
+    let ctx: &mut Context = as_mut!(ctx);
+    let out: &mut Image = as_mut!(out);
+    if imgs.is_null() {
+        return ErrorCode::NullPtr as i32;
+    }
+
+    let mut images = Vec::with_capacity(len);
+    for img in std::slice::from_raw_parts(imgs, len) {
+        let img: &mut Image = as_mut!(img);
+        images.push(img.clone());
+    }
+
+    match crate::ico::encode(&images) {
+        Ok(img) => {
+            *out = img;
+            0
+        }
+        Err(err) => {
+            ctx.last_error = Some(err);
+            ErrorCode::Encode as i32
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn qoi_seed() -> u32 {
+    crate::qoi::seed()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn qoi_decode(
+    ctx: *mut Context,
+    ptr: *const u8,
+    size: usize,
+    out: *mut Image,
+) -> i32 {
+    let ctx: &mut Context = as_mut!(ctx);
+    let out: &mut Image = as_mut!(out);
+    if ptr.is_null() {
+        return ErrorCode::NullPtr as i32;
+    }
+
+    let data = std::slice::from_raw_parts(ptr, size);
+    match crate::qoi::decode(data) {
+        Ok(img) => {
+            *out = img;
+            0
+        }
+        Err(err) => {
+            ctx.last_error = Some(err);
+            ErrorCode::Decode as i32
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn qoi_encode(ctx: *mut Context, img: *mut Image, out: *mut Image) -> i32 {
+    let ctx: &mut Context = as_mut!(ctx);
+    let img: &mut Image = as_mut!(img);
+    let out: &mut Image = as_mut!(out);
+
+    match crate::qoi::encode(img) {
+        Ok(img) => {
+            *out = img;
+            0
+        }
+        Err(err) => {
+            ctx.last_error = Some(err);
+            ErrorCode::Encode as i32
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pnm_seed() -> u32 {
+    crate::pnm::seed()
+}
+
+/// Decode a binary PGM, PPM or PAM.
+#[no_mangle]
+pub unsafe extern "C" fn pnm_decode(
+    ctx: *mut Context,
+    ptr: *const u8,
+    size: usize,
+    out: *mut Image,
+) -> i32 {
+    let ctx: &mut Context = as_mut!(ctx);
+    let out: &mut Image = as_mut!(out);
+    if ptr.is_null() {
+        return ErrorCode::NullPtr as i32;
+    }
+
+    let data = std::slice::from_raw_parts(ptr, size);
+    match crate::pnm::decode_with_options(data, &ctx.pnm_decode_options) {
+        Ok(img) => {
+            *out = img;
+            0
+        }
+        Err(err) => {
+            ctx.last_error = Some(err);
+            ErrorCode::Decode as i32
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ppm_encode(ctx: *mut Context, img: *mut Image, out: *mut Image) -> i32 {
+    let ctx: &mut Context = as_mut!(ctx);
+    let img: &mut Image = as_mut!(img);
+    let out: &mut Image = as_mut!(out);
+
+    match crate::pnm::encode_ppm(img) {
+        Ok(img) => {
+            *out = img;
+            0
+        }
+        Err(err) => {
+            ctx.last_error = Some(err);
+            ErrorCode::Encode as i32
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pam_encode(ctx: *mut Context, img: *mut Image, out: *mut Image) -> i32 {
+    let ctx: &mut Context = as_mut!(ctx);
+    let img: &mut Image = as_mut!(img);
+    let out: &mut Image = as_mut!(out);
+
+    match crate::pnm::encode_pam(img) {
+        Ok(img) => {
+            *out = img;
+            0
+        }
+        Err(err) => {
+            ctx.last_error = Some(err);
+            ErrorCode::Encode as i32
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pnm_set_decode_keep_16bit(ctx: *mut Context, keep_16bit: bool) {
+    if let Some(ctx) = ctx.as_mut() {
+        ctx.pnm_decode_options.keep_16bit = keep_16bit;
+    }
+}
+
+#[repr(i32)]
+pub enum ErrorCode {
+    /// Received an unexpected null pointer.
+    NullPtr = -1,
+
+    /// Failed to decode image.
+    Decode = -2,
+
+    /// Failed to encode image.
     Encode = -3,
 
     /// Failed to resize image.
     Resize = -4,
+
+    /// Failed to convert the colors of an image.
+    Color = -5,
+
+    /// Failed to rotate or flip an image.
+    Transform = -6,
+
+    /// Failed to crop an image.
+    Crop = -7,
 }
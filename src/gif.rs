@@ -0,0 +1,105 @@
+use gif::{ColorOutput, DecodeOptions, DisposalMethod, Repeat};
+
+use crate::animation::{Animation, Blend, Canvas, Dispose, Region};
+use crate::error::Error;
+use crate::{ColorType, Image, ImageFormat, ImageInfo};
+
+/// Frame delays up to this (in ms) are considered too short, and replaced by [`DEFAULT_DELAY`].
+const MIN_DELAY: u32 = 10;
+/// The delay (in ms) browsers play frames without a meaningful delay with.
+const DEFAULT_DELAY: u32 = 100;
+
+/// Decode the first frame of a GIF as RGBA8.
+pub fn decode(data: &[u8]) -> Result<Image, Error> {
+    let mut decoder = decoder(data)?;
+    let mut canvas = Canvas::new(u32::from(decoder.width()), u32::from(decoder.height()));
+    let frame = decoder
+        .read_next_frame()
+        .map_err(GifError::from)?
+        .ok_or(GifError::NoFrames)?;
+    canvas.draw(&frame.buffer, region(frame), Blend::Over, Dispose::None);
+
+    Ok(canvas.snapshot())
+}
+
+/// Read the basic properties of a GIF, by only reading the header and the descriptor of its first
+/// frame.
+pub fn probe(data: &[u8]) -> Result<ImageInfo, Error> {
+    let mut decoder = decoder(data)?;
+    let (width, height) = (u32::from(decoder.width()), u32::from(decoder.height()));
+    let frame = decoder
+        .next_frame_info()
+        .map_err(GifError::from)?
+        .ok_or(GifError::NoFrames)?;
+
+    Ok(ImageInfo {
+        width,
+        height,
+        format: ImageFormat::GIF,
+        color_type: ColorType::Indexed,
+        bit_depth: 8,
+        has_alpha: frame.transparent.is_some(),
+        orientation: 1,
+    })
+}
+
+/// Decode all frames of a GIF, composited onto the full canvas as RGBA8. Like browsers do, frame
+/// delays of 10ms or less (including none) are replaced by 100ms, as many GIFs rely on that.
+pub fn decode_animation(data: &[u8]) -> Result<Animation, Error> {
+    let mut decoder = decoder(data)?;
+    let (width, height) = (u32::from(decoder.width()), u32::from(decoder.height()));
+    let loop_count = match decoder.repeat() {
+        Repeat::Infinite => 0,
+        // the loop count of GIFs is the number of repetitions after the first play
+        Repeat::Finite(n) => u32::from(n) + 1,
+    };
+
+    let mut animation = Animation::new(width, height, loop_count);
+    let mut canvas = Canvas::new(width, height);
+    while let Some(frame) = decoder.read_next_frame().map_err(GifError::from)? {
+        let region = region(frame);
+        let dispose = match frame.dispose {
+            DisposalMethod::Any | DisposalMethod::Keep => Dispose::None,
+            DisposalMethod::Background => Dispose::Background,
+            DisposalMethod::Previous => Dispose::Previous,
+        };
+
+        canvas.draw(&frame.buffer, region, Blend::Over, dispose);
+        // GIF delays are in units of 10ms
+        let delay = match u32::from(frame.delay) * 10 {
+            0..=MIN_DELAY => DEFAULT_DELAY,
+            delay => delay,
+        };
+        animation.push(canvas.snapshot(), delay);
+        canvas.dispose(region, dispose);
+    }
+
+    if animation.frames().is_empty() {
+        return Err(GifError::NoFrames.into());
+    }
+
+    Ok(animation)
+}
+
+fn decoder(data: &[u8]) -> Result<gif::Decoder<&[u8]>, Error> {
+    let mut options = DecodeOptions::new();
+    options.set_color_output(ColorOutput::RGBA);
+    Ok(options.read_info(data).map_err(GifError::from)?)
+}
+
+fn region(frame: &gif::Frame<'_>) -> Region {
+    Region {
+        x: u32::from(frame.left),
+        y: u32::from(frame.top),
+        width: u32::from(frame.width),
+        height: u32::from(frame.height),
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum GifError {
+    #[error("failed to decode GIF image")]
+    Decode(#[from] gif::DecodingError),
+    #[error("GIF does not contain any frames")]
+    NoFrames,
+}
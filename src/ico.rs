@@ -0,0 +1,134 @@
+use crate::error::Error;
+use crate::{Image, ImageFormat};
+
+/// Images of at least this size are embedded as PNG, smaller ones as BMP (which is what older
+/// Windows versions expect for them).
+const PNG_MIN_SIZE: u32 = 64;
+
+pub fn seed() -> u32 {
+    1
+}
+
+/// Encode `images` into a single multi-resolution ICO. Each image must be at most 256x256 px.
+pub fn encode(images: &[Image]) -> Result<Image, Error> {
+    if images.is_empty() {
+        return Err(IcoError::NoImages.into());
+    }
+
+    let mut entries = Vec::with_capacity(images.len());
+    for img in images {
+        if img.width == 0 || img.height == 0 || img.width > 256 || img.height > 256 {
+            return Err(IcoError::InvalidSize {
+                width: img.width,
+                height: img.height,
+            }
+            .into());
+        }
+
+        let img = img.to_8bit();
+        let (data, bits_per_pixel) = if img.width >= PNG_MIN_SIZE || img.height >= PNG_MIN_SIZE {
+            let bits_per_pixel: u16 = if img.format == ImageFormat::RGBA8 {
+                32
+            } else {
+                24
+            };
+            (crate::png::encode(&img)?.as_ref().to_vec(), bits_per_pixel)
+        } else {
+            // always BGRA, see `bmp`
+            (bmp(&img)?, 32)
+        };
+        entries.push((img.width, img.height, bits_per_pixel, data));
+    }
+
+    const HEADER_SIZE: usize = 6;
+    const ENTRY_SIZE: usize = 16;
+    let mut offset = HEADER_SIZE + ENTRY_SIZE * entries.len();
+    let mut buf =
+        Vec::with_capacity(offset + entries.iter().map(|(_, _, _, d)| d.len()).sum::<usize>());
+
+    // ICONDIR
+    buf.extend_from_slice(&0u16.to_le_bytes()); // reserved
+    buf.extend_from_slice(&1u16.to_le_bytes()); // type: icon
+    buf.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+
+    // ICONDIRENTRY for each image
+    for (width, height, bits_per_pixel, data) in &entries {
+        // a size of 256 is stored as 0
+        buf.push(*width as u8);
+        buf.push(*height as u8);
+        buf.push(0); // no color palette
+        buf.push(0); // reserved
+        buf.extend_from_slice(&1u16.to_le_bytes()); // color planes
+        buf.extend_from_slice(&bits_per_pixel.to_le_bytes());
+        buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&(offset as u32).to_le_bytes());
+        offset += data.len();
+    }
+
+    let (width, height) = (entries[0].0, entries[0].1);
+    for (_, _, _, data) in entries {
+        buf.extend_from_slice(&data);
+    }
+
+    Ok(Image::new(buf, ImageFormat::ICO, width, height))
+}
+
+/// Encode `img` as the BMP variant used inside ICOs: a BITMAPINFOHEADER (with the height doubled
+/// to account for the AND mask), followed by bottom-up 32-bit BGRA pixels and the 1-bit AND mask.
+fn bmp(img: &Image) -> Result<Vec<u8>, Error> {
+    let pixel_size = match img.format {
+        ImageFormat::RGB8 => 3,
+        ImageFormat::RGBA8 => 4,
+        _ => {
+            return Err(Error::Process {
+                process: "encode as ICO",
+                format: img.format,
+            })
+        }
+    };
+
+    let (width, height) = (img.width as usize, img.height as usize);
+    // mask rows are padded to 32 bits
+    let mask_stride = width.div_ceil(32) * 4;
+    let mut buf = Vec::with_capacity(40 + width * height * 4 + mask_stride * height);
+
+    // BITMAPINFOHEADER
+    buf.extend_from_slice(&40u32.to_le_bytes());
+    buf.extend_from_slice(&(img.width as i32).to_le_bytes());
+    buf.extend_from_slice(&(img.height as i32 * 2).to_le_bytes());
+    buf.extend_from_slice(&1u16.to_le_bytes()); // color planes
+    buf.extend_from_slice(&32u16.to_le_bytes()); // bits per pixel
+    buf.extend_from_slice(&0u32.to_le_bytes()); // no compression
+    buf.extend_from_slice(&((width * height * 4) as u32).to_le_bytes());
+    buf.extend_from_slice(&[0; 16]); // resolution and palette, all unused
+
+    let data: &[u8] = img.as_ref();
+    let rows = || data.chunks_exact(width * pixel_size).rev();
+    for row in rows() {
+        for px in row.chunks_exact(pixel_size) {
+            let alpha = px.get(3).copied().unwrap_or(255);
+            buf.extend_from_slice(&[px[2], px[1], px[0], alpha]);
+        }
+    }
+
+    // AND mask, marking fully transparent pixels for clients that ignore the alpha channel
+    for row in rows() {
+        let mut mask = vec![0u8; mask_stride];
+        for (x, px) in row.chunks_exact(pixel_size).enumerate() {
+            if px.get(3) == Some(&0) {
+                mask[x / 8] |= 0x80 >> (x % 8);
+            }
+        }
+        buf.extend_from_slice(&mask);
+    }
+
+    Ok(buf)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum IcoError {
+    #[error("ICO requires at least one image")]
+    NoImages,
+    #[error("ICO images must be between 1x1 and 256x256 px, got {width}x{height}")]
+    InvalidSize { width: u32, height: u32 },
+}
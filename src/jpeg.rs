@@ -1,19 +1,49 @@
 use std::mem;
-use std::os::raw::{c_int, c_ulong};
+use std::os::raw::{c_int, c_uint, c_ulong};
 
 use crate::error::Error;
-use crate::{Image, ImageFormat};
+use crate::exif;
+use crate::metadata::{self, Allowlist, Metadata};
+use crate::transform::{mirror, Transform};
+use crate::{ColorType, Image, ImageFormat, ImageInfo};
 use jpeg::*;
 
 pub fn seed() -> u32 {
     1
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct DecodeOptions {
+    /// Convert the image to sRGB, according to its ICC profile. For CMYK images, this also
+    /// replaces the naive conversion to RGB with one according to their (CMYK) profile. Requires
+    /// the `color` feature, decoding fails otherwise.
+    pub to_srgb: bool,
+    /// The Exif and XMP metadata to keep, all other metadata is dropped. Nothing is kept by
+    /// default, as it might reveal e.g. where a photo was taken.
+    pub keep_metadata: Allowlist,
+}
+
 pub fn decode(data: &[u8]) -> Result<Image, Error> {
+    decode_with_options(data, &DecodeOptions::default())
+}
+
+pub fn decode_with_options(data: &[u8], opts: &DecodeOptions) -> Result<Image, Error> {
+    #[cfg(not(feature = "color"))]
+    if opts.to_srgb {
+        return Err(Error::ColorDisabled);
+    }
+
     // println!("decode");
 
     // extract rotation from Exif data
-    let rotation = extract_rotation(data);
+    let rotation = exif::orientation(data).and_then(Transform::from_exif);
+
+    let icc_profile = icc_profile(data);
+    #[cfg(feature = "color")]
+    let cmyk_transform = match &icc_profile {
+        Some(icc_profile) if opts.to_srgb => crate::color::cmyk_to_srgb(icc_profile)?,
+        _ => None,
+    };
 
     unsafe {
         let mut cinfo: jpeg_decompress_struct = std::mem::zeroed();
@@ -35,89 +65,84 @@ pub fn decode(data: &[u8]) -> Result<Image, Error> {
 
         // println!("width={}, height={}", cinfo.image_width, cinfo.image_height);
 
-        cinfo.out_color_space = J_COLOR_SPACE::JCS_RGB;
+        // libjpeg can't convert CMYK/YCCK to RGB, so read those as CMYK and convert them below
+        let mut cmyk = match cinfo.jpeg_color_space {
+            J_COLOR_SPACE::JCS_CMYK | J_COLOR_SPACE::JCS_YCCK => {
+                cinfo.out_color_space = J_COLOR_SPACE::JCS_CMYK;
+                Some(Cmyk {
+                    row: vec![0u8; cinfo.image_width as usize * 4],
+                    // Adobe applications write CMYK JPEGs with inverted values
+                    inverted: has_adobe_marker(data),
+                    #[cfg(feature = "color")]
+                    transform: cmyk_transform,
+                })
+            }
+            _ => {
+                cinfo.out_color_space = J_COLOR_SPACE::JCS_RGB;
+                None
+            }
+        };
         try_jpeg_start_decompress(&mut cinfo).into_result()?;
 
         let mut width = cinfo.image_width;
         let mut height = cinfo.image_height;
 
-        let row_stride = cinfo.image_width as usize * cinfo.output_components as usize;
+        let row_stride = cinfo.image_width as usize * 3;
         let buffer_size = row_stride * cinfo.image_height as usize;
         let mut buffer = vec![0u8; buffer_size];
 
         // Use the fact that the image is decoded row by row, to rotate it right away to save on
         // memory.
-        match rotation {
-            Some(Rotation::R90) => {
-                let mut row = vec![0u8; row_stride];
-
-                while cinfo.output_scanline < cinfo.output_height {
-                    let output_scanline = cinfo.output_scanline as usize;
-                    let mut jsamparray = [row.as_mut_ptr()];
-                    try_jpeg_read_scanlines(&mut cinfo, jsamparray.as_mut_ptr(), 1)
-                        .into_result()?;
-
-                    // first row becomes -> last column
-                    // first row + 1 becomes -> last column - 1
-                    // ... and so on
-                    let y_from = output_scanline;
-                    let x_to = height as usize - 1 - y_from;
-                    for x_from in 0..(width as usize) {
-                        let y_to = x_from;
-                        let from = x_from * 3;
-                        let to = (x_to + y_to * height as usize) * 3;
-                        buffer[to..(to + 3)].copy_from_slice(&row[from..(from + 3)]);
-                    }
+        let mirrors_rows = rotation.is_some_and(Transform::mirrors_rows);
+        let reverses_rows = rotation.is_some_and(Transform::reverses_rows);
+        if rotation.is_some_and(Transform::transposes) {
+            let mut row = vec![0u8; row_stride];
+
+            while cinfo.output_scanline < cinfo.output_height {
+                let output_scanline = cinfo.output_scanline as usize;
+                read_scanline(&mut cinfo, &mut row, cmyk.as_mut())?;
+
+                // for columns filled from the bottom, mirror the row
+                if mirrors_rows {
+                    mirror(&mut row, 3);
                 }
 
-                mem::swap(&mut width, &mut height);
-            }
-            Some(Rotation::R180) => {
-                while cinfo.output_scanline < cinfo.output_height {
-                    // start filling rows from the bottom
-                    let offset =
-                        (cinfo.image_height - 1 - cinfo.output_scanline) as usize * row_stride;
-                    let mut jsamparray = [buffer[offset..].as_mut_ptr()];
-                    try_jpeg_read_scanlines(&mut cinfo, jsamparray.as_mut_ptr(), 1)
-                        .into_result()?;
-
-                    // mirror row pixels
-                    rotate180::<3>(&mut buffer[offset..offset + row_stride]);
+                // first row becomes -> first column (or last column, if reversed)
+                // first row + 1 becomes -> first column + 1 (or last column - 1, if reversed)
+                // ... and so on
+                let y_from = output_scanline;
+                let x_to = if reverses_rows {
+                    height as usize - 1 - y_from
+                } else {
+                    y_from
+                };
+                for x_from in 0..(width as usize) {
+                    let y_to = x_from;
+                    let from = x_from * 3;
+                    let to = (x_to + y_to * height as usize) * 3;
+                    buffer[to..(to + 3)].copy_from_slice(&row[from..(from + 3)]);
                 }
             }
-            Some(Rotation::R270) => {
-                let mut row = vec![0u8; row_stride];
-
-                while cinfo.output_scanline < cinfo.output_height {
-                    let output_scanline = cinfo.output_scanline as usize;
-                    let mut jsamparray = [row.as_mut_ptr()];
-                    try_jpeg_read_scanlines(&mut cinfo, jsamparray.as_mut_ptr(), 1)
-                        .into_result()?;
-
-                    // first row becomes -> first column starting at the bottom
-                    // first row + 1 becomes -> first column + 1 starting at the bottom
-                    // ... and so on
-                    // but starting at the bottom, thus mirror the row
-                    rotate180::<3>(&mut row[..]);
-
-                    let y_from = output_scanline;
-                    let x_to = y_from;
-                    for x_from in 0..(width as usize) {
-                        let y_to = x_from;
-                        let from = x_from * 3;
-                        let to = (x_to + y_to * height as usize) * 3;
-                        buffer[to..(to + 3)].copy_from_slice(&row[from..(from + 3)]);
-                    }
-                }
 
-                mem::swap(&mut width, &mut height);
-            }
-            None => {
-                while cinfo.output_scanline < cinfo.output_height {
-                    let offset = cinfo.output_scanline as usize * row_stride;
-                    let mut jsamparray = [buffer[offset..].as_mut_ptr()];
-                    try_jpeg_read_scanlines(&mut cinfo, jsamparray.as_mut_ptr(), 1)
-                        .into_result()?;
+            mem::swap(&mut width, &mut height);
+        } else {
+            while cinfo.output_scanline < cinfo.output_height {
+                // if reversed, start filling rows from the bottom
+                let y_to = if reverses_rows {
+                    cinfo.image_height - 1 - cinfo.output_scanline
+                } else {
+                    cinfo.output_scanline
+                };
+                let offset = y_to as usize * row_stride;
+                read_scanline(
+                    &mut cinfo,
+                    &mut buffer[offset..offset + row_stride],
+                    cmyk.as_mut(),
+                )?;
+
+                // mirror row pixels
+                if mirrors_rows {
+                    mirror(&mut buffer[offset..offset + row_stride], 3);
                 }
             }
         }
@@ -125,10 +150,139 @@ pub fn decode(data: &[u8]) -> Result<Image, Error> {
         try_jpeg_finish_decompress(&mut cinfo).into_result()?;
         try_jpeg_destroy_decompress(&mut cinfo).into_result()?;
 
-        Ok(Image::new(buffer, ImageFormat::RGB8, width, height))
+        let mut img = Image::new(buffer, ImageFormat::RGB8, width, height);
+        // the profile of a CMYK JPEG doesn't apply to the converted RGB pixels
+        if cmyk.is_none() {
+            img.set_icc_profile(icc_profile.filter(|icc| crate::is_rgb_icc_profile(icc)));
+        }
+        if !opts.keep_metadata.is_empty() {
+            let mut metadata = read_metadata(data);
+            metadata.retain(&opts.keep_metadata);
+            img.set_metadata(Some(metadata));
+        }
+
+        #[cfg(feature = "color")]
+        if opts.to_srgb {
+            return crate::color::to_srgb(img);
+        }
+
+        Ok(img)
     }
 }
 
+/// Read the basic properties of a JPEG, by only reading its header.
+pub fn probe(data: &[u8]) -> Result<ImageInfo, Error> {
+    let orientation = exif::orientation(data);
+
+    let (width, height, color_space) = unsafe {
+        let mut cinfo: jpeg_decompress_struct = std::mem::zeroed();
+
+        let mut err: wimg_error_mgr = std::mem::zeroed();
+        #[cfg(not(target_family = "wasm"))]
+        {
+            cinfo.common.err = throwing_error_mgr(&mut err);
+        }
+        #[cfg(target_family = "wasm")]
+        {
+            cinfo.common.err = jpeg_std_error(&mut err.r#pub);
+        }
+
+        try_jpeg_create_decompress(&mut cinfo).into_result()?;
+        try_jpeg_mem_src(&mut cinfo, data.as_ptr(), data.len() as c_ulong).into_result()?;
+        try_jpeg_read_header(&mut cinfo, true as boolean).into_result()?;
+        let header = (
+            cinfo.image_width,
+            cinfo.image_height,
+            cinfo.jpeg_color_space,
+        );
+        try_jpeg_destroy_decompress(&mut cinfo).into_result()?;
+        header
+    };
+
+    // decoding applies the orientation
+    let transposes = orientation
+        .and_then(Transform::from_exif)
+        .is_some_and(Transform::transposes);
+    let (width, height) = if transposes {
+        (height, width)
+    } else {
+        (width, height)
+    };
+
+    Ok(ImageInfo {
+        width,
+        height,
+        format: ImageFormat::JPEG,
+        color_type: match color_space {
+            J_COLOR_SPACE::JCS_GRAYSCALE => ColorType::Gray,
+            J_COLOR_SPACE::JCS_CMYK | J_COLOR_SPACE::JCS_YCCK => ColorType::CMYK,
+            _ => ColorType::RGB,
+        },
+        bit_depth: precision(data).unwrap_or(8),
+        has_alpha: false,
+        orientation: crate::info_orientation(orientation),
+    })
+}
+
+/// The sample precision of the frame header (SOF0-SOF15, except DHT, JPG and DAC).
+fn precision(data: &[u8]) -> Option<u8> {
+    exif::segments(data)
+        .find(|segment| {
+            matches!(segment.marker, 0xC0..=0xCF) && ![0xC4, 0xC8, 0xCC].contains(&segment.marker)
+        })
+        .and_then(|segment| segment.data.first().copied())
+}
+
+/// Scanline buffer for CMYK (and YCCK, which libjpeg converts to CMYK) JPEGs.
+struct Cmyk {
+    row: Vec<u8>,
+    inverted: bool,
+    /// Conversion according to the image's CMYK profile, used instead of the naive one.
+    #[cfg(feature = "color")]
+    transform: Option<Box<moxcms::Transform8BitExecutor>>,
+}
+
+/// Read the next scanline into `row` as RGB8. CMYK scanlines are read into `cmyk.row` first and
+/// converted to RGB.
+unsafe fn read_scanline(
+    cinfo: &mut jpeg_decompress_struct,
+    row: &mut [u8],
+    cmyk: Option<&mut Cmyk>,
+) -> Result<(), Error> {
+    let Some(cmyk) = cmyk else {
+        let mut jsamparray = [row.as_mut_ptr()];
+        try_jpeg_read_scanlines(cinfo, jsamparray.as_mut_ptr(), 1).into_result()?;
+        return Ok(());
+    };
+
+    let mut jsamparray = [cmyk.row.as_mut_ptr()];
+    try_jpeg_read_scanlines(cinfo, jsamparray.as_mut_ptr(), 1).into_result()?;
+
+    #[cfg(feature = "color")]
+    if let Some(transform) = &cmyk.transform {
+        // ICC profiles expect 0 to mean no ink
+        if cmyk.inverted {
+            cmyk.row.iter_mut().for_each(|v| *v = 255 - *v);
+        }
+        transform
+            .transform(&cmyk.row, row)
+            .map_err(crate::color::ColorError::Transform)?;
+        return Ok(());
+    }
+
+    for (rgb, px) in row.chunks_exact_mut(3).zip(cmyk.row.chunks_exact(4)) {
+        let px = [px[0], px[1], px[2], px[3]];
+        let px = if cmyk.inverted {
+            px.map(|v| 255 - v)
+        } else {
+            px
+        };
+        rgb.copy_from_slice(&crate::cmyk_to_rgb(px));
+    }
+
+    Ok(())
+}
+
 #[derive(Debug, Clone)]
 pub struct EncodeOptions {
     /// 0-100 scale
@@ -138,6 +292,10 @@ pub struct EncodeOptions {
 pub fn encode(img: &Image, opts: &EncodeOptions) -> Result<Image, Error> {
     // println!("encode {} {}", img.width, img.height);
 
+    // JPEG only supports 8-bit samples
+    let img = img.to_8bit();
+    let img: &Image = &img;
+
     let (in_color_space, input_components) = match img.format {
         ImageFormat::RGB8 => (J_COLOR_SPACE::JCS_RGB, 3),
         ImageFormat::RGBA8 => (J_COLOR_SPACE::JCS_EXT_RGBA, 4),
@@ -177,6 +335,36 @@ pub fn encode(img: &Image, opts: &EncodeOptions) -> Result<Image, Error> {
 
         try_jpeg_start_compress(&mut cinfo, true as boolean).into_result()?;
 
+        if let Some(metadata) = img.metadata() {
+            let exif = metadata.exif.as_deref().map(|exif| (EXIF_HEADER, exif));
+            let xmp = metadata
+                .xmp
+                .as_deref()
+                .map(|xmp| (XMP_HEADER, xmp.as_bytes()));
+            for (header, payload) in exif.into_iter().chain(xmp) {
+                // unlike ICC profiles, Exif and XMP can't be split across multiple segments
+                let segment = [header, payload].concat();
+                if segment.len() > MAX_SEGMENT_LEN {
+                    return Err(Error::Process {
+                        process: "embed oversized metadata into",
+                        format: ImageFormat::JPEG,
+                    });
+                }
+                try_jpeg_write_marker(&mut cinfo, 0xE1, segment.as_ptr(), segment.len() as c_uint)
+                    .into_result()?;
+            }
+        }
+
+        if let Some(icc_profile) = img.icc_profile() {
+            // split into APP2 segments by libjpeg
+            try_jpeg_write_icc_profile(
+                &mut cinfo,
+                icc_profile.as_ptr(),
+                icc_profile.len() as c_uint,
+            )
+            .into_result()?;
+        }
+
         let row_stride = cinfo.image_width as usize * cinfo.input_components as usize;
         let buffer = img.as_ref();
         while cinfo.next_scanline < cinfo.image_height {
@@ -199,122 +387,57 @@ impl Default for EncodeOptions {
     }
 }
 
-#[derive(Debug)]
-enum Rotation {
-    R90,
-    R180,
-    R270,
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+const XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+/// The maximum payload of a segment, as its length (including the two length bytes) is a u16.
+const MAX_SEGMENT_LEN: usize = u16::MAX as usize - 2;
+
+/// Read the Exif and XMP metadata of the APP1 segments. The orientation is reset in both, as it is
+/// applied to the decoded pixels.
+fn read_metadata(data: &[u8]) -> Metadata {
+    let exif = exif::jpeg_exif(data).map(|exif| {
+        let mut exif = exif.to_vec();
+        exif::reset_orientation(&mut exif);
+        exif
+    });
+    let xmp = exif::segments(data)
+        .filter(|segment| segment.marker == 0xE1)
+        .find_map(|segment| segment.data.strip_prefix(XMP_HEADER))
+        .and_then(|xmp| std::str::from_utf8(xmp).ok())
+        .map(metadata::reset_xmp_orientation);
+    Metadata { exif, xmp }
 }
 
-fn extract_rotation(data: &[u8]) -> Option<Rotation> {
-    // search exif data orientation
-    // - EXIF file format: https://www.media.mit.edu/pia/Research/deepview/exif.html
-
-    let mut offset = 2; // skip SOI (Start Of Image) marker
-    while let Ok::<[u8; 4], _>(marker) = data[offset..offset + 4].try_into() {
-        // markers are supposed to be prefixed with `0xFF`, if not, stop looking for Exif data
-        if marker[0] != 0xFF {
-            break;
-        }
+/// Reassemble the ICC profile, which is split across (up to 255) APP2 segments.
+fn icc_profile(data: &[u8]) -> Option<Vec<u8>> {
+    const HEADER: &[u8] = b"ICC_PROFILE\0";
 
-        // ignore any marker except the application marker 1 (used by Exif)
-        let size = usize::from(u16::from_be_bytes([marker[2], marker[3]]));
-        if marker[1] != 0xE1 {
-            offset += size + 2;
-            continue;
-        }
-
-        let app1 = &data[offset + 4..offset + 4 + (size - 2)];
-        const HEADER: &[u8] = b"Exif\0\0";
-        if !app1.starts_with(HEADER) {
-            // not exif after all
-            break;
-        }
-
-        offset += size + 2;
-
-        let app1 = &app1[HEADER.len()..];
-        let is_be = app1[0..2] == [0x4d, 0x4d];
-
-        let h: [u8; 2] = app1[2..4].try_into().ok()?;
-        let h = if is_be {
-            u16::from_be_bytes(h)
-        } else {
-            u16::from_le_bytes(h)
-        };
-        if h != 0x002a {
-            break;
-        }
-
-        let ifd0_offset: [u8; 4] = app1[4..8].try_into().ok()?;
-        let ifd0_offset = if is_be {
-            u32::from_be_bytes(ifd0_offset)
-        } else {
-            u32::from_le_bytes(ifd0_offset)
-        } as usize;
-
-        // parse IFD0
-        let entry_count: [u8; 2] = app1[ifd0_offset..ifd0_offset + 2].try_into().ok()?;
-        let entry_count = if is_be {
-            u16::from_be_bytes(entry_count)
-        } else {
-            u16::from_le_bytes(entry_count)
-        } as usize;
-
-        // iterate tags
-        for i in 0..entry_count {
-            let offset = ifd0_offset + 2 + i * 12;
-            let tag: [u8; 2] = app1[offset..offset + 2].try_into().ok()?;
-            let tag = if is_be {
-                u16::from_be_bytes(tag)
-            } else {
-                u16::from_le_bytes(tag)
-            };
-            if tag == 0x0112 {
-                let offset = offset + 2 + 2 + 4;
-                let val: [u8; 2] = app1[offset..offset + 2].try_into().ok()?;
-                let val = if is_be {
-                    u16::from_be_bytes(val)
-                } else {
-                    u16::from_le_bytes(val)
-                };
-
-                return match val {
-                    3 => Some(Rotation::R180),
-                    6 => Some(Rotation::R90),
-                    8 => Some(Rotation::R270),
-                    _ => None,
-                };
-            }
+    // each chunk is prefixed with its 1-based sequence number and the total number of chunks
+    let mut chunks = Vec::new();
+    for segment in exif::segments(data).filter(|segment| segment.marker == 0xE2) {
+        if let Some(&[seq, count, ref chunk @ ..]) = segment.data.strip_prefix(HEADER) {
+            chunks.push((seq, count, chunk));
         }
     }
-
-    None
+    chunks.sort_by_key(|(seq, _, _)| *seq);
+
+    // ignore incomplete or inconsistent profiles
+    let count = chunks.first()?.1;
+    let complete = chunks.len() == usize::from(count)
+        && chunks
+            .iter()
+            .enumerate()
+            .all(|(i, (seq, c, _))| usize::from(*seq) == i + 1 && *c == count);
+    complete.then(|| {
+        chunks
+            .iter()
+            .flat_map(|(_, _, chunk)| *chunk)
+            .copied()
+            .collect()
+    })
 }
 
-/// Rotate by 180deg in place.
-///
-/// # Panics
-///
-/// Panics if `data.len()` is not a multiple of `PIXEL_SIZE`.
-fn rotate180<const PIXEL_SIZE: usize>(data: &mut [u8]) {
-    assert_eq!(
-        data.len() % PIXEL_SIZE,
-        0,
-        "data length must be a multiple of the pixel size"
-    );
-
-    let pixel_count = data.len() / PIXEL_SIZE;
-    for i in 0..(pixel_count / 2) {
-        swap_pixel::<PIXEL_SIZE>(data, i, pixel_count - 1 - i);
-    }
-}
-
-/// Swap pixels. index `b` must be greater than index `a`
-fn swap_pixel<const PIXEL_SIZE: usize>(data: &mut [u8], a: usize, b: usize) {
-    let p1 = a * PIXEL_SIZE;
-    let p2 = b * PIXEL_SIZE;
-
-    let (l, r) = data.split_at_mut(p2);
-    l[p1..p1 + PIXEL_SIZE].swap_with_slice(&mut r[..PIXEL_SIZE]);
+/// Whether the JPEG contains an Adobe APP14 segment.
+fn has_adobe_marker(data: &[u8]) -> bool {
+    exif::segments(data).any(|segment| segment.marker == 0xEE && segment.data.starts_with(b"Adobe"))
 }
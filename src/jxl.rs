@@ -0,0 +1,302 @@
+use jxl_oxide::{JxlImage, PixelFormat};
+#[cfg(not(feature = "libjxl"))]
+use zune_core::bit_depth::BitDepth;
+#[cfg(not(feature = "libjxl"))]
+use zune_core::colorspace::ColorSpace;
+#[cfg(not(feature = "libjxl"))]
+use zune_core::options::EncoderOptions;
+#[cfg(not(feature = "libjxl"))]
+use zune_jpegxl::JxlSimpleEncoder;
+
+use crate::error::Error;
+use crate::{ColorType, Image, ImageFormat, ImageInfo};
+
+pub fn seed() -> u32 {
+    1
+}
+
+/// Decode the first frame of a JPEG XL image.
+pub fn decode(data: &[u8]) -> Result<Image, Error> {
+    let image = JxlImage::builder().read(data).map_err(JxlError::Decode)?;
+    let render = image.render_frame(0).map_err(JxlError::Decode)?;
+    let mut stream = render.stream();
+    let (width, height) = (stream.width(), stream.height());
+
+    let mut buf = vec![0u8; width as usize * height as usize * stream.channels() as usize];
+    stream.write_to_buffer(&mut buf);
+
+    let (buf, image_format) = match image.pixel_format() {
+        PixelFormat::Rgb => (buf, ImageFormat::RGB8),
+        PixelFormat::Rgba => (buf, ImageFormat::RGBA8),
+        // expand grayscale to RGB, so that all further processing only needs to support RGB(A)
+        PixelFormat::Gray => (
+            buf.iter().flat_map(|&v| [v, v, v]).collect(),
+            ImageFormat::RGB8,
+        ),
+        PixelFormat::Graya => (
+            buf.chunks_exact(2)
+                .flat_map(|px| [px[0], px[0], px[0], px[1]])
+                .collect(),
+            ImageFormat::RGBA8,
+        ),
+        pixel_format => return Err(JxlError::UnsupportedPixelFormat(pixel_format).into()),
+    };
+
+    Ok(Image::new(buf, image_format, width, height))
+}
+
+/// Read the basic properties of a JPEG XL image, by only parsing its headers.
+pub fn probe(data: &[u8]) -> Result<ImageInfo, Error> {
+    let image = JxlImage::builder().read(data).map_err(JxlError::Decode)?;
+    let metadata = &image.image_header().metadata;
+
+    let color_type = match image.pixel_format() {
+        PixelFormat::Gray => ColorType::Gray,
+        PixelFormat::Graya => ColorType::GrayAlpha,
+        PixelFormat::Rgb => ColorType::RGB,
+        PixelFormat::Rgba => ColorType::RGBA,
+        PixelFormat::Cmyk | PixelFormat::Cmyka => ColorType::CMYK,
+    };
+    Ok(ImageInfo {
+        // with the orientation applied, as by `decode`
+        width: image.width(),
+        height: image.height(),
+        format: ImageFormat::JXL,
+        color_type,
+        bit_depth: metadata.bit_depth.bits_per_sample() as u8,
+        has_alpha: image.pixel_format().has_alpha(),
+        orientation: crate::info_orientation(u16::try_from(metadata.orientation).ok()),
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct EncodeOptions {
+    /// 0 (worst) - 100 (best), mapped onto libjxl's butteraugli distance; ignored when encoding
+    /// losslessly
+    pub quality: u16,
+    /// 1 (fast) - 9 (slow but better compression)
+    pub effort: u8,
+    /// Lossy encoding requires the `libjxl` feature.
+    pub lossless: bool,
+    /// The number of threads to encode with, 0 to only use the calling thread.
+    pub threads: u8,
+}
+
+/// Encode `img` as JPEG XL. Without the `libjxl` feature, only lossless encoding is supported.
+pub fn encode(img: &Image, opts: &EncodeOptions) -> Result<Image, Error> {
+    let (channels, depth) = match img.format {
+        ImageFormat::RGB8 => (3, 8),
+        ImageFormat::RGBA8 => (4, 8),
+        // 16-bit samples are expected in native byte order, like they are stored in `Image`
+        ImageFormat::RGB16 => (3, 16),
+        ImageFormat::RGBA16 => (4, 16),
+        _ => {
+            return Err(Error::Process {
+                process: "encode as JPEG XL",
+                format: img.format,
+            })
+        }
+    };
+
+    #[cfg(feature = "libjxl")]
+    let buf = encode_libjxl(img, channels, depth, opts)?;
+    #[cfg(not(feature = "libjxl"))]
+    let buf = encode_lossless(img, channels, depth, opts)?;
+
+    Ok(Image::new(buf, ImageFormat::JXL, img.width, img.height))
+}
+
+#[cfg(not(feature = "libjxl"))]
+fn encode_lossless(
+    img: &Image,
+    channels: u32,
+    depth: u32,
+    opts: &EncodeOptions,
+) -> Result<Vec<u8>, Error> {
+    if !opts.lossless {
+        return Err(JxlError::LossyUnsupported.into());
+    }
+
+    let colorspace = match channels {
+        3 => ColorSpace::RGB,
+        _ => ColorSpace::RGBA,
+    };
+    let depth = match depth {
+        8 => BitDepth::Eight,
+        _ => BitDepth::Sixteen,
+    };
+    let options = EncoderOptions::new(img.width as usize, img.height as usize, colorspace, depth)
+        // map the 1-9 effort onto the encoder's 0-127 scale
+        .set_effort(opts.effort.clamp(1, 9) * 14)
+        .set_num_threads(opts.threads);
+    let mut buf = Vec::new();
+    JxlSimpleEncoder::new(img.as_ref(), options)
+        .encode(&mut buf)
+        .map_err(JxlError::Encode)?;
+
+    Ok(buf)
+}
+
+/// A libjxl encoder and its thread pool, destroyed on drop.
+#[cfg(feature = "libjxl")]
+struct Libjxl {
+    enc: *mut crate::libjxl::JxlEncoder,
+    runner: *mut std::ffi::c_void,
+}
+
+#[cfg(feature = "libjxl")]
+impl Drop for Libjxl {
+    fn drop(&mut self) {
+        unsafe {
+            crate::libjxl::JxlEncoderDestroy(self.enc);
+            if !self.runner.is_null() {
+                crate::libjxl::JxlThreadParallelRunnerDestroy(self.runner);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "libjxl")]
+fn encode_libjxl(
+    img: &Image,
+    channels: u32,
+    depth: u32,
+    opts: &EncodeOptions,
+) -> Result<Vec<u8>, Error> {
+    use crate::libjxl::*;
+
+    let check = |status| match status {
+        JXL_ENC_SUCCESS => Ok(()),
+        _ => Err(JxlError::Libjxl),
+    };
+
+    unsafe {
+        let enc = JxlEncoderCreate(std::ptr::null());
+        if enc.is_null() {
+            return Err(Error::ExceedsMemory);
+        }
+        let mut libjxl = Libjxl {
+            enc,
+            runner: std::ptr::null_mut(),
+        };
+        if opts.threads > 0 {
+            libjxl.runner =
+                JxlThreadParallelRunnerCreate(std::ptr::null(), usize::from(opts.threads));
+            if libjxl.runner.is_null() {
+                return Err(Error::ExceedsMemory);
+            }
+            check(JxlEncoderSetParallelRunner(
+                enc,
+                JxlThreadParallelRunner,
+                libjxl.runner,
+            ))?;
+        }
+
+        let mut info = std::mem::MaybeUninit::<JxlBasicInfo>::uninit();
+        JxlEncoderInitBasicInfo(info.as_mut_ptr());
+        let mut info = info.assume_init();
+        info.xsize = img.width;
+        info.ysize = img.height;
+        info.bits_per_sample = depth;
+        info.num_color_channels = 3;
+        if channels == 4 {
+            info.num_extra_channels = 1;
+            info.alpha_bits = depth;
+        }
+        // lossless encoding must keep the original color space instead of converting to XYB
+        info.uses_original_profile = opts.lossless.into();
+        check(JxlEncoderSetBasicInfo(enc, &info))?;
+        // without a profile, libjxl assumes sRGB
+        if let Some(icc_profile) = img.icc_profile() {
+            check(JxlEncoderSetICCProfile(
+                enc,
+                icc_profile.as_ptr(),
+                icc_profile.len(),
+            ))?;
+        }
+
+        let settings = JxlEncoderFrameSettingsCreate(enc, std::ptr::null());
+        if settings.is_null() {
+            return Err(JxlError::Libjxl.into());
+        }
+        check(JxlEncoderFrameSettingsSetOption(
+            settings,
+            JXL_ENC_FRAME_SETTING_EFFORT,
+            opts.effort.clamp(1, 9).into(),
+        ))?;
+        if opts.lossless {
+            check(JxlEncoderSetFrameLossless(settings, 1))?;
+        } else {
+            let distance = JxlEncoderDistanceFromQuality(opts.quality.min(100).into());
+            check(JxlEncoderSetFrameDistance(settings, distance))?;
+        }
+
+        let format = JxlPixelFormat {
+            num_channels: channels,
+            data_type: match depth {
+                8 => JXL_TYPE_UINT8,
+                _ => JXL_TYPE_UINT16,
+            },
+            endianness: JXL_NATIVE_ENDIAN,
+            align: 0,
+        };
+        let data = img.as_ref();
+        check(JxlEncoderAddImageFrame(
+            settings,
+            &format,
+            data.as_ptr().cast(),
+            data.len(),
+        ))?;
+        JxlEncoderCloseInput(enc);
+
+        let mut buf = vec![0u8; data.len() / 4 + 1024];
+        let mut written = 0;
+        loop {
+            let mut next_out = buf.as_mut_ptr().add(written);
+            let mut avail_out = buf.len() - written;
+            let status = JxlEncoderProcessOutput(enc, &mut next_out, &mut avail_out);
+            written = buf.len() - avail_out;
+            match status {
+                JXL_ENC_NEED_MORE_OUTPUT => buf.resize(buf.len() * 2, 0),
+                status => {
+                    check(status)?;
+                    break;
+                }
+            }
+        }
+        buf.truncate(written);
+
+        Ok(buf)
+    }
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        Self {
+            quality: 80,
+            effort: 7,
+            lossless: false,
+            // threads can't be spawned in wasm, use the encoder's default elsewhere
+            threads: if cfg!(target_family = "wasm") {
+                0
+            } else {
+                zune_core::options::EncoderOptions::default().num_threads()
+            },
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum JxlError {
+    #[error("failed to decode JPEG XL image")]
+    Decode(#[source] Box<dyn std::error::Error + Send + Sync>),
+    #[error("failed to encode image as JPEG XL")]
+    Encode(#[source] zune_jpegxl::JxlEncodeErrors),
+    #[error("lossy JPEG XL encoding requires the `libjxl` feature")]
+    LossyUnsupported,
+    #[cfg(feature = "libjxl")]
+    #[error("libjxl failed to encode image")]
+    Libjxl,
+    #[error("unsupported pixel format: {0:?}")]
+    UnsupportedPixelFormat(PixelFormat),
+}
@@ -1,20 +1,39 @@
 #![allow(clippy::missing_safety_doc)]
 
+pub mod animation;
+mod attention;
 pub mod avif;
-mod crop;
+#[cfg(feature = "color")]
+pub mod color;
+pub mod crop;
 pub mod error;
+pub mod exif;
 #[cfg(feature = "ffi")]
 pub mod ffi;
+pub mod gif;
 pub mod hash;
+pub mod ico;
 pub mod jpeg;
+pub mod jxl;
+#[cfg(feature = "libjxl")]
+/// cbindgen:ignore
+mod libjxl;
+pub mod metadata;
 pub mod png;
+pub mod pnm;
+pub mod qoi;
 pub mod resize;
+pub mod tiff;
+pub mod transform;
 pub mod webp;
 
+use std::borrow::Cow;
 use std::fmt::Display;
 #[cfg(feature = "ffi")]
 use std::ptr::NonNull;
 
+use metadata::{Allowlist, Metadata};
+
 #[cfg(feature = "ffi")]
 #[repr(C)]
 #[derive(Debug)]
@@ -25,6 +44,11 @@ pub struct Image {
     format: ImageFormat,
     width: u32,
     height: u32,
+    /// Embedded ICC profile (null if there is none), see [`Image::icc_profile`].
+    icc_ptr: Option<NonNull<u8>>,
+    icc_len: usize,
+    /// Exif and XMP metadata (null if there is none), see [`Image::metadata`].
+    metadata: Option<Box<Metadata>>,
 }
 
 #[cfg(not(feature = "ffi"))]
@@ -34,10 +58,12 @@ pub struct Image {
     format: ImageFormat,
     width: u32,
     height: u32,
+    icc_profile: Option<Vec<u8>>,
+    metadata: Option<Box<Metadata>>,
 }
 
 #[repr(u8)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ImageFormat {
     RGB8 = 1,
     RGBA8,
@@ -45,6 +71,51 @@ pub enum ImageFormat {
     PNG,
     AVIF,
     WEBP,
+    /// 16-bit samples in native byte order
+    RGB16,
+    /// 16-bit samples in native byte order
+    RGBA16,
+    GIF,
+    JXL,
+    TIFF,
+    ICO,
+    QOI,
+    PPM,
+    PAM,
+}
+
+/// The color model of an encoded image, i.e. before decoders convert it to RGB(A).
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorType {
+    Gray = 1,
+    GrayAlpha,
+    RGB,
+    RGBA,
+    /// Palette based, e.g. GIFs
+    Indexed,
+    CMYK,
+}
+
+/// The basic properties of an encoded image, see [`probe`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageInfo {
+    /// The width of the decoded image, i.e. after decoders that apply the orientation (JPEG and
+    /// JPEG XL) did so.
+    pub width: u32,
+    /// The height of the decoded image, see [`ImageInfo::width`].
+    pub height: u32,
+    pub format: ImageFormat,
+    pub color_type: ColorType,
+    /// Bits per sample
+    pub bit_depth: u8,
+    /// Whether the image has an alpha channel or a transparent color (which decoders expand into
+    /// an alpha channel).
+    pub has_alpha: bool,
+    /// The orientation (1-8, as defined by Exif) stored in the image, 1 if there is none. AVIF
+    /// transformations (`irot` and `imir`) are not considered.
+    pub orientation: u8,
 }
 
 #[repr(u8)]
@@ -52,11 +123,13 @@ pub enum ImageFormat {
 enum PixelFormat {
     RGB8 = 1,
     RGBA8 = 2,
+    RGB16 = 3,
+    RGBA16 = 4,
 }
 
 impl Image {
     #[cfg(feature = "ffi")]
-    fn new(v: Vec<u8>, format: ImageFormat, width: u32, height: u32) -> Self {
+    pub fn new(v: Vec<u8>, format: ImageFormat, width: u32, height: u32) -> Self {
         let mut v = std::mem::ManuallyDrop::new(v);
         Self {
             ptr: unsafe { NonNull::new_unchecked(v.as_mut_ptr()) },
@@ -65,6 +138,9 @@ impl Image {
             format,
             width,
             height,
+            icc_ptr: None,
+            icc_len: 0,
+            metadata: None,
         }
     }
 
@@ -80,6 +156,8 @@ impl Image {
             format,
             width,
             height,
+            icc_profile: None,
+            metadata: None,
         }
     }
 
@@ -91,16 +169,118 @@ impl Image {
         self.height
     }
 
+    /// The embedded ICC color profile, if any.
+    pub fn icc_profile(&self) -> Option<&[u8]> {
+        #[cfg(feature = "ffi")]
+        {
+            self.icc_ptr
+                .map(|ptr| unsafe { std::slice::from_raw_parts(ptr.as_ptr(), self.icc_len) })
+        }
+        #[cfg(not(feature = "ffi"))]
+        self.icc_profile.as_deref()
+    }
+
+    /// Set (or remove) the ICC color profile, which is embedded by encoders that support it.
+    pub fn set_icc_profile(&mut self, icc_profile: Option<Vec<u8>>) {
+        let icc_profile = icc_profile.filter(|icc_profile| !icc_profile.is_empty());
+        #[cfg(feature = "ffi")]
+        {
+            self.drop_icc_profile();
+            if let Some(icc_profile) = icc_profile {
+                let icc_profile = std::mem::ManuallyDrop::new(icc_profile.into_boxed_slice());
+                self.icc_len = icc_profile.len();
+                self.icc_ptr = NonNull::new(icc_profile.as_ptr() as *mut u8);
+            }
+        }
+        #[cfg(not(feature = "ffi"))]
+        {
+            self.icc_profile = icc_profile;
+        }
+    }
+
+    /// Set the ICC profile, e.g. to carry the profile of a source image over to a processed one.
+    pub(crate) fn with_icc_profile(mut self, icc_profile: Option<&[u8]>) -> Self {
+        self.set_icc_profile(icc_profile.map(<[u8]>::to_vec));
+        self
+    }
+
+    /// The Exif and XMP metadata, if any.
+    pub fn metadata(&self) -> Option<&Metadata> {
+        self.metadata.as_deref()
+    }
+
+    /// Set (or remove) the metadata, which is embedded by encoders that support it.
+    pub fn set_metadata(&mut self, metadata: Option<Metadata>) {
+        self.metadata = metadata
+            .filter(|metadata| !metadata.is_empty())
+            .map(Box::new);
+    }
+
+    /// Drop all metadata that isn't in `allowlist`, e.g. to keep the copyright notice but not the
+    /// location.
+    pub fn retain_metadata(&mut self, allowlist: &Allowlist) {
+        if let Some(mut metadata) = self.metadata.take() {
+            metadata.retain(allowlist);
+            self.set_metadata(Some(*metadata));
+        }
+    }
+
+    /// Set the metadata, e.g. to carry the metadata of a source image over to a processed one.
+    pub(crate) fn with_metadata(mut self, metadata: Option<&Metadata>) -> Self {
+        self.set_metadata(metadata.cloned());
+        self
+    }
+
+    #[cfg(feature = "ffi")]
+    fn drop_icc_profile(&mut self) {
+        if let Some(ptr) = self.icc_ptr.take() {
+            unsafe {
+                drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+                    ptr.as_ptr(),
+                    self.icc_len,
+                )));
+            }
+        }
+        self.icc_len = 0;
+    }
+
     pub fn mime_type(&self) -> &'static str {
         match self.format {
-            ImageFormat::RGB8 | ImageFormat::RGBA8 => "application/octet-stream",
+            ImageFormat::RGB8 | ImageFormat::RGBA8 | ImageFormat::RGB16 | ImageFormat::RGBA16 => {
+                "application/octet-stream"
+            }
             ImageFormat::JPEG => "image/jpeg",
             ImageFormat::PNG => "image/png",
             ImageFormat::AVIF => "image/avif",
             ImageFormat::WEBP => "image/webp",
+            ImageFormat::GIF => "image/gif",
+            ImageFormat::JXL => "image/jxl",
+            ImageFormat::TIFF => "image/tiff",
+            ImageFormat::ICO => "image/x-icon",
+            ImageFormat::QOI => "image/qoi",
+            ImageFormat::PPM => "image/x-portable-pixmap",
+            ImageFormat::PAM => "image/x-portable-arbitrarymap",
         }
     }
 
+    /// Convert 16-bit images to 8 bit. Other images are returned as they are.
+    pub(crate) fn to_8bit(&self) -> Cow<'_, Image> {
+        let format = match self.format {
+            ImageFormat::RGB16 => ImageFormat::RGB8,
+            ImageFormat::RGBA16 => ImageFormat::RGBA8,
+            _ => return Cow::Borrowed(self),
+        };
+        let data = samples16(self.as_ref())
+            .into_iter()
+            .map(|v| ((u32::from(v) * 255 + 32767) / 65535) as u8)
+            .collect();
+        Cow::Owned(
+            Image::new(data, format, self.width, self.height)
+                .with_icc_profile(self.icc_profile())
+                .with_metadata(self.metadata()),
+        )
+    }
+
     pub fn into_vec(self) -> Vec<u8> {
         #[cfg(feature = "ffi")]
         unsafe {
@@ -116,10 +296,133 @@ impl PixelFormat {
         match self {
             PixelFormat::RGB8 => 3,
             PixelFormat::RGBA8 => 4,
+            PixelFormat::RGB16 => 6,
+            PixelFormat::RGBA16 => 8,
         }
     }
 }
 
+/// Detect the format of an encoded image from its magic bytes.
+pub fn detect_format(data: &[u8]) -> Option<ImageFormat> {
+    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some(ImageFormat::JPEG)
+    } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some(ImageFormat::PNG)
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        Some(ImageFormat::GIF)
+    } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+        Some(ImageFormat::WEBP)
+    } else if is_avif(data) {
+        Some(ImageFormat::AVIF)
+    } else if data.starts_with(&[0xFF, 0x0A]) || data.starts_with(b"\0\0\0\x0cJXL \r\n\x87\n") {
+        // either a bare codestream or the ISOBMFF based container
+        Some(ImageFormat::JXL)
+    } else if data.starts_with(b"II*\0") || data.starts_with(b"MM\0*") {
+        Some(ImageFormat::TIFF)
+    } else if data.starts_with(b"qoif") {
+        Some(ImageFormat::QOI)
+    } else if is_netpbm(data, b"P5") || is_netpbm(data, b"P6") {
+        // binary PGMs are reported as PPM, as both are decoded the same way
+        Some(ImageFormat::PPM)
+    } else if is_netpbm(data, b"P7") {
+        Some(ImageFormat::PAM)
+    } else {
+        None
+    }
+}
+
+/// Whether `data` starts with an ISOBMFF `ftyp` box listing an AVIF brand.
+fn is_avif(data: &[u8]) -> bool {
+    if data.get(4..8) != Some(b"ftyp") {
+        return false;
+    }
+    let Some(size) = data.get(0..4) else {
+        return false;
+    };
+    let size = u32::from_be_bytes([size[0], size[1], size[2], size[3]]) as usize;
+    let Some(ftyp) = data.get(8..size.min(data.len())) else {
+        return false;
+    };
+
+    // major brand, minor version, followed by the compatible brands
+    ftyp.chunks_exact(4)
+        .enumerate()
+        .filter(|(i, _)| *i != 1)
+        .any(|(_, brand)| brand == b"avif" || brand == b"avis")
+}
+
+/// Whether `data` starts with the Netpbm `magic` number, followed by whitespace.
+fn is_netpbm(data: &[u8], magic: &[u8; 2]) -> bool {
+    data.starts_with(magic) && data.get(2).is_some_and(u8::is_ascii_whitespace)
+}
+
+/// Decode an image of any of the supported formats, detected via [`detect_format`]. For animated
+/// images, only the first frame is returned.
+pub fn decode(data: &[u8]) -> Result<Image, error::Error> {
+    match detect_format(data) {
+        Some(ImageFormat::JPEG) => jpeg::decode(data),
+        Some(ImageFormat::PNG) => png::decode(data),
+        Some(ImageFormat::GIF) => gif::decode(data),
+        Some(ImageFormat::WEBP) => webp::decode(data),
+        Some(ImageFormat::AVIF) => avif::decode(data),
+        Some(ImageFormat::JXL) => jxl::decode(data),
+        Some(ImageFormat::TIFF) => tiff::decode(data),
+        Some(ImageFormat::QOI) => qoi::decode(data),
+        Some(ImageFormat::PPM | ImageFormat::PAM) => pnm::decode(data),
+        _ => Err(error::Error::UnknownFormat),
+    }
+}
+
+/// Read the basic properties of an image of any of the supported formats, detected via
+/// [`detect_format`]. Only the headers are parsed, i.e. the image isn't decoded.
+pub fn probe(data: &[u8]) -> Result<ImageInfo, error::Error> {
+    match detect_format(data) {
+        Some(ImageFormat::JPEG) => jpeg::probe(data),
+        Some(ImageFormat::PNG) => png::probe(data),
+        Some(ImageFormat::GIF) => gif::probe(data),
+        Some(ImageFormat::WEBP) => webp::probe(data),
+        Some(ImageFormat::AVIF) => avif::probe(data),
+        Some(ImageFormat::JXL) => jxl::probe(data),
+        Some(ImageFormat::TIFF) => tiff::probe(data),
+        Some(ImageFormat::QOI) => qoi::probe(data),
+        Some(ImageFormat::PPM | ImageFormat::PAM) => pnm::probe(data),
+        _ => Err(error::Error::UnknownFormat),
+    }
+}
+
+/// An Exif orientation as stored in [`ImageInfo::orientation`], i.e. 1 if it is missing or
+/// invalid.
+pub(crate) fn info_orientation(orientation: Option<u16>) -> u8 {
+    orientation
+        .filter(|orientation| (1..=8).contains(orientation))
+        .map_or(1, |orientation| orientation as u8)
+}
+
+/// Whether `icc_profile` describes RGB data. As grayscale images are expanded to RGB while decoding,
+/// their (grayscale) profiles do not apply anymore.
+pub(crate) fn is_rgb_icc_profile(icc_profile: &[u8]) -> bool {
+    // the data color space is part of the profile header
+    icc_profile.get(16..20) == Some(b"RGB ")
+}
+
+/// Naively convert a CMYK pixel (with 0 meaning no ink) to RGB, i.e. without a CMYK profile.
+pub(crate) fn cmyk_to_rgb([c, m, y, k]: [u8; 4]) -> [u8; 3] {
+    let white = 255 - u32::from(k);
+    [c, m, y].map(|v| ((255 - u32::from(v)) * white / 255) as u8)
+}
+
+/// Read native-endian 16-bit samples from `data`.
+pub(crate) fn samples16(data: &[u8]) -> Vec<u16> {
+    data.chunks_exact(2)
+        .map(|b| u16::from_ne_bytes([b[0], b[1]]))
+        .collect()
+}
+
+/// Write 16-bit samples as native-endian bytes.
+pub(crate) fn bytes16(samples: &[u16]) -> Vec<u8> {
+    samples.iter().flat_map(|v| v.to_ne_bytes()).collect()
+}
+
 impl AsRef<[u8]> for Image {
     fn as_ref(&self) -> &[u8] {
         #[cfg(feature = "ffi")]
@@ -148,12 +451,15 @@ impl Drop for Image {
         unsafe {
             Vec::from_raw_parts(self.ptr.as_ptr(), self.len as usize, self.cap as usize);
         }
+        self.drop_icc_profile();
     }
 }
 
 impl Clone for Image {
     fn clone(&self) -> Self {
         Image::new(self.as_ref().to_vec(), self.format, self.width, self.height)
+            .with_icc_profile(self.icc_profile())
+            .with_metadata(self.metadata())
     }
 }
 
@@ -166,6 +472,15 @@ impl Display for ImageFormat {
             ImageFormat::PNG => "PNG",
             ImageFormat::AVIF => "AVIF",
             ImageFormat::WEBP => "WEBP",
+            ImageFormat::RGB16 => "RGB16",
+            ImageFormat::RGBA16 => "RGBA16",
+            ImageFormat::GIF => "GIF",
+            ImageFormat::JXL => "JXL",
+            ImageFormat::TIFF => "TIFF",
+            ImageFormat::ICO => "ICO",
+            ImageFormat::QOI => "QOI",
+            ImageFormat::PPM => "PPM",
+            ImageFormat::PAM => "PAM",
         })
     }
 }
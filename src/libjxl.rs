@@ -0,0 +1,152 @@
+//! Bindings to the parts of the libjxl encoder API used for lossy JPEG XL encoding. The library is
+//! linked by the build script.
+
+#![allow(non_camel_case_types)]
+
+use std::ffi::c_void;
+
+pub type JXL_BOOL = i32;
+pub type JxlEncoderStatus = i32;
+
+pub const JXL_ENC_SUCCESS: JxlEncoderStatus = 0;
+pub const JXL_ENC_NEED_MORE_OUTPUT: JxlEncoderStatus = 2;
+
+/// `JxlEncoderFrameSettingId::JXL_ENC_FRAME_SETTING_EFFORT`
+pub const JXL_ENC_FRAME_SETTING_EFFORT: i32 = 0;
+
+pub const JXL_TYPE_UINT8: i32 = 2;
+pub const JXL_TYPE_UINT16: i32 = 3;
+pub const JXL_NATIVE_ENDIAN: i32 = 0;
+
+#[repr(C)]
+pub struct JxlEncoder {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+pub struct JxlEncoderFrameSettings {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+pub struct JxlPixelFormat {
+    pub num_channels: u32,
+    pub data_type: i32,
+    pub endianness: i32,
+    pub align: usize,
+}
+
+#[repr(C)]
+pub struct JxlPreviewHeader {
+    pub xsize: u32,
+    pub ysize: u32,
+}
+
+#[repr(C)]
+pub struct JxlAnimationHeader {
+    pub tps_numerator: u32,
+    pub tps_denominator: u32,
+    pub num_loops: u32,
+    pub have_timecodes: JXL_BOOL,
+}
+
+/// Initialized with [`JxlEncoderInitBasicInfo`].
+#[repr(C)]
+pub struct JxlBasicInfo {
+    pub have_container: JXL_BOOL,
+    pub xsize: u32,
+    pub ysize: u32,
+    pub bits_per_sample: u32,
+    pub exponent_bits_per_sample: u32,
+    pub intensity_target: f32,
+    pub min_nits: f32,
+    pub relative_to_max_display: JXL_BOOL,
+    pub linear_below: f32,
+    pub uses_original_profile: JXL_BOOL,
+    pub have_preview: JXL_BOOL,
+    pub have_animation: JXL_BOOL,
+    pub orientation: i32,
+    pub num_color_channels: u32,
+    pub num_extra_channels: u32,
+    pub alpha_bits: u32,
+    pub alpha_exponent_bits: u32,
+    pub alpha_premultiplied: JXL_BOOL,
+    pub preview: JxlPreviewHeader,
+    pub animation: JxlAnimationHeader,
+    pub intrinsic_xsize: u32,
+    pub intrinsic_ysize: u32,
+    pub padding: [u8; 100],
+}
+
+pub type JxlParallelRunner = unsafe extern "C" fn(
+    runner_opaque: *mut c_void,
+    jpegxl_opaque: *mut c_void,
+    init: *mut c_void,
+    func: *mut c_void,
+    start_range: u32,
+    end_range: u32,
+) -> i32;
+
+extern "C" {
+    pub fn JxlEncoderCreate(memory_manager: *const c_void) -> *mut JxlEncoder;
+    pub fn JxlEncoderDestroy(enc: *mut JxlEncoder);
+    pub fn JxlEncoderSetParallelRunner(
+        enc: *mut JxlEncoder,
+        parallel_runner: JxlParallelRunner,
+        parallel_runner_opaque: *mut c_void,
+    ) -> JxlEncoderStatus;
+    pub fn JxlEncoderInitBasicInfo(info: *mut JxlBasicInfo);
+    pub fn JxlEncoderSetBasicInfo(
+        enc: *mut JxlEncoder,
+        info: *const JxlBasicInfo,
+    ) -> JxlEncoderStatus;
+    pub fn JxlEncoderSetICCProfile(
+        enc: *mut JxlEncoder,
+        icc_profile: *const u8,
+        size: usize,
+    ) -> JxlEncoderStatus;
+    pub fn JxlEncoderFrameSettingsCreate(
+        enc: *mut JxlEncoder,
+        source: *const JxlEncoderFrameSettings,
+    ) -> *mut JxlEncoderFrameSettings;
+    pub fn JxlEncoderFrameSettingsSetOption(
+        frame_settings: *mut JxlEncoderFrameSettings,
+        option: i32,
+        value: i64,
+    ) -> JxlEncoderStatus;
+    pub fn JxlEncoderSetFrameLossless(
+        frame_settings: *mut JxlEncoderFrameSettings,
+        lossless: JXL_BOOL,
+    ) -> JxlEncoderStatus;
+    pub fn JxlEncoderSetFrameDistance(
+        frame_settings: *mut JxlEncoderFrameSettings,
+        distance: f32,
+    ) -> JxlEncoderStatus;
+    pub fn JxlEncoderDistanceFromQuality(quality: f32) -> f32;
+    pub fn JxlEncoderAddImageFrame(
+        frame_settings: *const JxlEncoderFrameSettings,
+        pixel_format: *const JxlPixelFormat,
+        buffer: *const c_void,
+        size: usize,
+    ) -> JxlEncoderStatus;
+    pub fn JxlEncoderCloseInput(enc: *mut JxlEncoder);
+    pub fn JxlEncoderProcessOutput(
+        enc: *mut JxlEncoder,
+        next_out: *mut *mut u8,
+        avail_out: *mut usize,
+    ) -> JxlEncoderStatus;
+
+    pub fn JxlThreadParallelRunnerCreate(
+        memory_manager: *const c_void,
+        num_worker_threads: usize,
+    ) -> *mut c_void;
+    pub fn JxlThreadParallelRunner(
+        runner_opaque: *mut c_void,
+        jpegxl_opaque: *mut c_void,
+        init: *mut c_void,
+        func: *mut c_void,
+        start_range: u32,
+        end_range: u32,
+    ) -> i32;
+    pub fn JxlThreadParallelRunnerDestroy(runner_opaque: *mut c_void);
+}
@@ -0,0 +1,99 @@
+//! Exif and XMP metadata, which is read by the JPEG and PNG decoders (only as far as their
+//! `keep_metadata` option allows) and written by the JPEG, PNG, WebP and AVIF encoders.
+
+use crate::exif;
+
+/// Exif and XMP metadata of an image.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    /// The Exif data as TIFF structure, i.e. without the `Exif\0\0` header used in JPEGs. The
+    /// orientation is reset to 1 by decoders that apply it to the pixels, i.e. by the JPEG one.
+    pub exif: Option<Vec<u8>>,
+    /// The XMP packet.
+    pub xmp: Option<String>,
+}
+
+/// The metadata to keep, see [`Metadata::retain`]. The default keeps nothing.
+#[derive(Debug, Clone, Default)]
+pub struct Allowlist {
+    /// The Exif tags of IFD0 and the Exif IFD to keep, e.g. [`exif::ARTIST`]. The GPS tags are
+    /// only kept if [`exif::GPS_IFD`] is listed.
+    pub exif_tags: Vec<u16>,
+    /// Keep the XMP packet. It is kept as a whole, so it might also contain e.g. the location.
+    pub xmp: bool,
+}
+
+impl Allowlist {
+    /// Only keep the creator and the copyright notice.
+    pub fn copyright() -> Self {
+        Allowlist {
+            exif_tags: vec![exif::ARTIST, exif::COPYRIGHT],
+            xmp: false,
+        }
+    }
+
+    /// Whether nothing is kept at all.
+    pub fn is_empty(&self) -> bool {
+        self.exif_tags.is_empty() && !self.xmp
+    }
+}
+
+impl Metadata {
+    pub fn is_empty(&self) -> bool {
+        self.exif.is_none() && self.xmp.is_none()
+    }
+
+    /// Drop all metadata that isn't in `allowlist`.
+    pub fn retain(&mut self, allowlist: &Allowlist) {
+        self.exif = self
+            .exif
+            .as_deref()
+            .and_then(|exif| exif::retain(exif, &allowlist.exif_tags));
+        if !allowlist.xmp {
+            self.xmp = None;
+        }
+    }
+}
+
+/// Set the `tiff:Orientation` of the XMP packet `xmp` to 1, written either as attribute (e.g.
+/// `tiff:Orientation="6"`) or as element (e.g. `<tiff:Orientation>6</tiff:Orientation>`).
+pub(crate) fn reset_xmp_orientation(xmp: &str) -> String {
+    const NAME: &str = "tiff:Orientation";
+
+    let mut out = String::with_capacity(xmp.len());
+    let mut rest = xmp;
+    while let Some(pos) = rest.find(NAME) {
+        let is_end_tag = rest[..pos].ends_with("</");
+        let (head, tail) = rest.split_at(pos + NAME.len());
+        out.push_str(head);
+        rest = tail;
+        if is_end_tag {
+            continue;
+        }
+
+        // the byte range of the value within `tail`
+        let trimmed = tail.trim_start();
+        let value = if let Some(attr) = trimmed.strip_prefix('=') {
+            let attr = attr.trim_start();
+            let Some(quote) = attr.chars().next().filter(|c| matches!(c, '"' | '\'')) else {
+                continue;
+            };
+            let start = tail.len() - attr.len() + 1;
+            tail[start..].find(quote).map(|len| start..start + len)
+        } else if trimmed.starts_with('>') {
+            let start = tail.len() - trimmed.len() + 1;
+            tail[start..].find('<').map(|len| start..start + len)
+        } else {
+            None
+        };
+        let Some(value) = value else {
+            continue;
+        };
+
+        out.push_str(&tail[..value.start]);
+        out.push('1');
+        rest = &tail[value.end..];
+    }
+    out.push_str(rest);
+    out
+}
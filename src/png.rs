@@ -1,49 +1,308 @@
+use std::borrow::Cow;
 use std::io::Cursor;
 
+use crate::animation::{Animation, Blend, Canvas, Dispose, Region};
 use crate::error::Error;
-use crate::{Image, ImageFormat};
-use png::{BitDepth, ColorType, Decoder, Encoder, Transformations};
+use crate::metadata::{Allowlist, Metadata};
+use crate::{bytes16, samples16, Image, ImageFormat, ImageInfo};
+use png::text_metadata::ITXtChunk;
+use png::{BitDepth, BlendOp, ColorType, Decoder, DisposeOp, Encoder, Info, Transformations};
 
 pub fn seed() -> u32 {
     1
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct DecodeOptions {
+    /// Decode 16-bit PNGs as RGB16/RGBA16 instead of reducing them to 8 bit.
+    pub keep_16bit: bool,
+    /// Convert the image to sRGB, according to its ICC profile or `cHRM`/`gAMA` chunks. Requires
+    /// the `color` feature, decoding fails otherwise.
+    pub to_srgb: bool,
+    /// The Exif and XMP metadata to keep, all other metadata is dropped. Nothing is kept by
+    /// default, as it might reveal e.g. where a photo was taken.
+    pub keep_metadata: Allowlist,
+}
+
+/// Decode a PNG image. For animated PNGs, only the default image is returned.
 pub fn decode(data: &[u8]) -> Result<Image, Error> {
+    decode_with_options(data, &DecodeOptions::default())
+}
+
+/// Decode a PNG image. For animated PNGs, only the default image is returned.
+pub fn decode_with_options(data: &[u8], opts: &DecodeOptions) -> Result<Image, Error> {
+    #[cfg(not(feature = "color"))]
+    if opts.to_srgb {
+        return Err(Error::ColorDisabled);
+    }
+
+    let data = Cursor::new(data);
+    let mut decoder = Decoder::new(data);
+    decoder.set_transformations(if opts.keep_16bit {
+        Transformations::EXPAND
+    } else {
+        Transformations::STRIP_16 | Transformations::EXPAND
+    });
+    let mut reader = decoder.read_info().map_err(PngError::from)?;
+    let icc_profile = reader
+        .info()
+        .icc_profile
+        .as_deref()
+        .filter(|icc| crate::is_rgb_icc_profile(icc))
+        .map(<[u8]>::to_vec);
+    let mut img = decode_frame(&mut reader)?;
+    img.set_icc_profile(icc_profile);
+    if !opts.keep_metadata.is_empty() {
+        let mut metadata = read_metadata(reader.info());
+        metadata.retain(&opts.keep_metadata);
+        img.set_metadata(Some(metadata));
+    }
+
+    #[cfg(feature = "color")]
+    if opts.to_srgb {
+        // the `cHRM`/`gAMA` chunks are only used for the conversion, they don't become a profile
+        // the image carries
+        if img.icc_profile().is_none() {
+            img.set_icc_profile(chunks_icc_profile(reader.info()));
+        }
+        return crate::color::to_srgb(img);
+    }
+
+    Ok(img)
+}
+
+/// Read the basic properties of a PNG, by only reading the chunks preceding the image data.
+pub fn probe(data: &[u8]) -> Result<ImageInfo, Error> {
+    let reader = Decoder::new(Cursor::new(data))
+        .read_info()
+        .map_err(PngError::from)?;
+    let info = reader.info();
+
+    let orientation = info.exif_metadata.as_deref().and_then(|exif| {
+        crate::exif::tiff_orientation(exif.strip_prefix(b"Exif\0\0").unwrap_or(exif))
+    });
+    let color_type = match info.color_type {
+        ColorType::Grayscale => crate::ColorType::Gray,
+        ColorType::GrayscaleAlpha => crate::ColorType::GrayAlpha,
+        ColorType::Rgb => crate::ColorType::RGB,
+        ColorType::Rgba => crate::ColorType::RGBA,
+        ColorType::Indexed => crate::ColorType::Indexed,
+    };
+    Ok(ImageInfo {
+        width: info.width,
+        height: info.height,
+        format: ImageFormat::PNG,
+        color_type,
+        bit_depth: info.bit_depth as u8,
+        // a `tRNS` chunk is expanded into an alpha channel
+        has_alpha: matches!(
+            color_type,
+            crate::ColorType::GrayAlpha | crate::ColorType::RGBA
+        ) || info.trns.is_some(),
+        orientation: crate::info_orientation(orientation),
+    })
+}
+
+/// The keyword of the `iTXt` chunk holding the XMP packet.
+const XMP_KEYWORD: &str = "XML:com.adobe.xmp";
+
+/// Read the Exif data of the `eXIf` chunk and the XMP packet of the `iTXt` chunk. As PNG decoding
+/// doesn't apply the Exif orientation, it is kept as is.
+fn read_metadata(info: &Info) -> Metadata {
+    let exif = info.exif_metadata.as_deref().map(|exif| {
+        // some encoders wrongly include the header used in JPEGs
+        exif.strip_prefix(b"Exif\0\0").unwrap_or(exif).to_vec()
+    });
+    let xmp = info
+        .utf8_text
+        .iter()
+        .find(|chunk| chunk.keyword == XMP_KEYWORD)
+        .and_then(|chunk| chunk.get_text().ok());
+    Metadata { exif, xmp }
+}
+
+/// An ICC profile describing the color space given by the `cHRM` and `gAMA` chunks. There is none
+/// if the image has an `sRGB` chunk (or neither of the others), as it is sRGB then.
+#[cfg(feature = "color")]
+fn chunks_icc_profile(info: &Info) -> Option<Vec<u8>> {
+    if info.srgb.is_some() || (info.chrm_chunk.is_none() && info.gama_chunk.is_none()) {
+        return None;
+    }
+
+    let chromaticities = info.chrm_chunk.map(|chrm| {
+        [chrm.white, chrm.red, chrm.green, chrm.blue].map(|(x, y)| (x.into_value(), y.into_value()))
+    });
+    crate::color::icc_profile_from_chromaticities(
+        chromaticities,
+        info.gama_chunk.map(|gamma| gamma.into_value()),
+    )
+}
+
+/// Decode all frames of an animated PNG, composited onto the full canvas as RGBA8. Non-animated
+/// PNGs result in a single frame.
+pub fn decode_animation(data: &[u8]) -> Result<Animation, Error> {
     let data = Cursor::new(data);
     let mut decoder = Decoder::new(data);
     decoder.set_transformations(Transformations::STRIP_16 | Transformations::EXPAND);
     let mut reader = decoder.read_info().map_err(PngError::from)?;
-    if reader.info().frame_control.is_some() {
-        return Err(PngError::UnsupportedAnimation.into());
+
+    let info = reader.info();
+    let (width, height) = (info.width, info.height);
+    let Some(animation_control) = info.animation_control else {
+        let mut animation = Animation::new(width, height, 0);
+        animation.push(into_rgba(decode_frame(&mut reader)?)?, 0);
+        return Ok(animation);
+    };
+
+    // the default image is not part of the animation if it isn't preceded by a frame control
+    if info.frame_control.is_none() {
+        decode_frame(&mut reader)?;
     }
 
+    let mut animation = Animation::new(width, height, animation_control.num_plays);
+    let mut canvas = Canvas::new(width, height);
+    for i in 0..animation_control.num_frames {
+        let frame = decode_frame(&mut reader)?;
+        let fc = reader
+            .info()
+            .frame_control
+            .ok_or(PngError::MissingFrameControl)?;
+        let region = Region {
+            x: fc.x_offset,
+            y: fc.y_offset,
+            width: fc.width,
+            height: fc.height,
+        };
+        let blend = match fc.blend_op {
+            BlendOp::Source => Blend::Source,
+            BlendOp::Over => Blend::Over,
+        };
+        let dispose = match fc.dispose_op {
+            DisposeOp::None => Dispose::None,
+            DisposeOp::Background => Dispose::Background,
+            // a previous disposal on the first frame is treated like a background disposal
+            DisposeOp::Previous if i == 0 => Dispose::Background,
+            DisposeOp::Previous => Dispose::Previous,
+        };
+
+        canvas.draw(into_rgba(frame)?.as_ref(), region, blend, dispose);
+        let delay_den = if fc.delay_den == 0 { 100 } else { fc.delay_den };
+        animation.push(
+            canvas.snapshot(),
+            u32::from(fc.delay_num) * 1000 / u32::from(delay_den),
+        );
+        canvas.dispose(region, dispose);
+    }
+
+    Ok(animation)
+}
+
+fn decode_frame<R: std::io::BufRead + std::io::Seek>(
+    reader: &mut png::Reader<R>,
+) -> Result<Image, Error> {
     let mut buf = vec![0; reader.output_buffer_size().ok_or(Error::ExceedsMemory)?];
     let info = reader.next_frame(&mut buf).map_err(PngError::from)?;
 
-    let image_format = match info.color_type {
-        ColorType::Rgb => ImageFormat::RGB8,
-        ColorType::Rgba => ImageFormat::RGBA8,
+    buf.resize(info.buffer_size(), 0);
+
+    if info.bit_depth == BitDepth::Sixteen {
+        // PNG samples are big-endian
+        let samples: Vec<u16> = buf
+            .chunks_exact(2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .collect();
+        let (samples, image_format) = match info.color_type {
+            ColorType::Rgb => (samples, ImageFormat::RGB16),
+            ColorType::Rgba => (samples, ImageFormat::RGBA16),
+            ColorType::Grayscale => (
+                samples.iter().flat_map(|&v| [v, v, v]).collect(),
+                ImageFormat::RGB16,
+            ),
+            ColorType::GrayscaleAlpha => (
+                samples
+                    .chunks_exact(2)
+                    .flat_map(|px| [px[0], px[0], px[0], px[1]])
+                    .collect(),
+                ImageFormat::RGBA16,
+            ),
+            _ => return Err(PngError::UnsupportedColorType(info.color_type).into()),
+        };
+        return Ok(Image::new(
+            bytes16(&samples),
+            image_format,
+            info.width,
+            info.height,
+        ));
+    }
+
+    let (buf, image_format) = match info.color_type {
+        ColorType::Rgb => (buf, ImageFormat::RGB8),
+        ColorType::Rgba => (buf, ImageFormat::RGBA8),
+        // expand grayscale to RGB, so that all further processing only needs to support RGB(A)
+        ColorType::Grayscale => (
+            buf.iter().flat_map(|&v| [v, v, v]).collect(),
+            ImageFormat::RGB8,
+        ),
+        ColorType::GrayscaleAlpha => (
+            buf.chunks_exact(2)
+                .flat_map(|px| [px[0], px[0], px[0], px[1]])
+                .collect(),
+            ImageFormat::RGBA8,
+        ),
         _ => return Err(PngError::UnsupportedColorType(info.color_type).into()),
     };
 
-    buf.resize(info.buffer_size(), 0);
-
     Ok(Image::new(buf, image_format, info.width, info.height))
 }
 
+fn into_rgba(img: Image) -> Result<Image, Error> {
+    match img.format {
+        ImageFormat::RGBA8 => Ok(img),
+        ImageFormat::RGB8 => {
+            let mut data = Vec::with_capacity(img.as_ref().len() / 3 * 4);
+            for px in img.as_ref().chunks_exact(3) {
+                data.extend_from_slice(px);
+                data.push(255);
+            }
+            Ok(Image::new(data, ImageFormat::RGBA8, img.width, img.height))
+        }
+        _ => Err(PngError::InvalidSource(img.format).into()),
+    }
+}
+
 pub fn encode(img: &Image) -> Result<Image, Error> {
     let mut buf = Vec::new();
-    let mut encoder = Encoder::new(&mut buf, img.width, img.height);
-    encoder.set_color(match img.format {
-        ImageFormat::RGB8 => ColorType::Rgb,
-        ImageFormat::RGBA8 => ColorType::Rgba,
+    let (color, depth) = match img.format {
+        ImageFormat::RGB8 => (ColorType::Rgb, BitDepth::Eight),
+        ImageFormat::RGBA8 => (ColorType::Rgba, BitDepth::Eight),
+        ImageFormat::RGB16 => (ColorType::Rgb, BitDepth::Sixteen),
+        ImageFormat::RGBA16 => (ColorType::Rgba, BitDepth::Sixteen),
         _ => return Err(PngError::InvalidSource(img.format).into()),
-    });
-    encoder.set_depth(BitDepth::Eight);
+    };
+    let mut info = Info::with_size(img.width, img.height);
+    info.color_type = color;
+    info.bit_depth = depth;
+    info.icc_profile = img.icc_profile().map(Cow::Borrowed);
+    if let Some(metadata) = img.metadata() {
+        info.exif_metadata = metadata.exif.as_deref().map(Cow::Borrowed);
+        if let Some(xmp) = &metadata.xmp {
+            info.utf8_text.push(ITXtChunk::new(XMP_KEYWORD, xmp));
+        }
+    }
+    let encoder = Encoder::with_info(&mut buf, info).map_err(PngError::from)?;
     let mut writer = encoder.write_header().map_err(PngError::from)?;
-    writer
-        .write_image_data(img.as_ref())
-        .map_err(PngError::from)?;
+    if depth == BitDepth::Sixteen {
+        // PNG samples are big-endian
+        let data: Vec<u8> = samples16(img.as_ref())
+            .into_iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect();
+        writer.write_image_data(&data).map_err(PngError::from)?;
+    } else {
+        writer
+            .write_image_data(img.as_ref())
+            .map_err(PngError::from)?;
+    }
     std::mem::drop(writer);
 
     Ok(Image::new(buf, ImageFormat::PNG, img.width, img.height))
@@ -57,8 +316,8 @@ pub enum PngError {
     Encode(#[from] png::EncodingError),
     #[error("unsupported color type: {0:?}")]
     UnsupportedColorType(ColorType),
-    #[error("animated PNGs are not supported")]
-    UnsupportedAnimation,
+    #[error("animation frame is missing its frame control")]
+    MissingFrameControl,
     #[error("cannot encode {0} as PNG")]
     InvalidSource(ImageFormat),
 }
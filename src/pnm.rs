@@ -0,0 +1,312 @@
+use crate::error::Error;
+use crate::{bytes16, samples16, ColorType, Image, ImageFormat, ImageInfo};
+
+pub fn seed() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DecodeOptions {
+    /// Decode images with a maxval above 255 as RGB16/RGBA16 instead of reducing them to 8 bit.
+    pub keep_16bit: bool,
+}
+
+/// Decode a binary Netpbm image, i.e. a PGM (`P5`), PPM (`P6`) or PAM (`P7`).
+pub fn decode(data: &[u8]) -> Result<Image, Error> {
+    decode_with_options(data, &DecodeOptions::default())
+}
+
+pub fn decode_with_options(data: &[u8], opts: &DecodeOptions) -> Result<Image, Error> {
+    let header = Header::parse(data)?;
+    let sample_size = if header.maxval > 255 { 2 } else { 1 };
+    let len = (header.width as usize)
+        .checked_mul(header.height as usize)
+        .and_then(|len| len.checked_mul(header.depth * sample_size))
+        .ok_or(Error::ExceedsMemory)?;
+    let raster = data
+        .get(header.offset..)
+        .and_then(|raster| raster.get(..len))
+        .ok_or(PnmError::Truncated)?;
+
+    // scale samples to the full range of 8 or 16 bit
+    let max = if sample_size == 2 { 65535 } else { 255 };
+    let samples: Vec<u16> = if sample_size == 2 {
+        raster
+            .chunks_exact(2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .collect()
+    } else {
+        raster.iter().map(|&v| u16::from(v)).collect()
+    };
+    let samples = if u32::from(header.maxval) == max {
+        samples
+    } else {
+        let maxval = u32::from(header.maxval);
+        samples
+            .into_iter()
+            .map(|v| ((u32::from(v).min(maxval) * max + maxval / 2) / maxval) as u16)
+            .collect()
+    };
+
+    // expand grayscale to RGB, so that all further processing only needs to support RGB(A)
+    let samples = match header.depth {
+        1 => samples.iter().flat_map(|&v| [v, v, v]).collect(),
+        2 => samples
+            .chunks_exact(2)
+            .flat_map(|px| [px[0], px[0], px[0], px[1]])
+            .collect(),
+        _ => samples,
+    };
+    let has_alpha = header.depth == 2 || header.depth == 4;
+
+    let img = if sample_size == 2 {
+        let img = Image::new(
+            bytes16(&samples),
+            if has_alpha {
+                ImageFormat::RGBA16
+            } else {
+                ImageFormat::RGB16
+            },
+            header.width,
+            header.height,
+        );
+        if opts.keep_16bit {
+            img
+        } else {
+            img.to_8bit().into_owned()
+        }
+    } else {
+        Image::new(
+            samples.into_iter().map(|v| v as u8).collect(),
+            if has_alpha {
+                ImageFormat::RGBA8
+            } else {
+                ImageFormat::RGB8
+            },
+            header.width,
+            header.height,
+        )
+    };
+
+    Ok(img)
+}
+
+/// Read the basic properties of a binary Netpbm image, by only parsing its header.
+pub fn probe(data: &[u8]) -> Result<ImageInfo, Error> {
+    let header = Header::parse(data)?;
+    let color_type = match header.depth {
+        1 => ColorType::Gray,
+        2 => ColorType::GrayAlpha,
+        3 => ColorType::RGB,
+        _ => ColorType::RGBA,
+    };
+
+    Ok(ImageInfo {
+        width: header.width,
+        height: header.height,
+        format: if data.starts_with(b"P7") {
+            ImageFormat::PAM
+        } else {
+            ImageFormat::PPM
+        },
+        color_type,
+        // the bits needed for maxval, e.g. 1 for bitmaps
+        bit_depth: (u16::BITS - header.maxval.leading_zeros()) as u8,
+        has_alpha: matches!(color_type, ColorType::GrayAlpha | ColorType::RGBA),
+        orientation: 1,
+    })
+}
+
+/// Encode `img` as binary PPM (`P6`). PPMs cannot carry alpha, so use [`encode_pam`] for RGBA
+/// images.
+pub fn encode_ppm(img: &Image) -> Result<Image, Error> {
+    let maxval = match img.format {
+        ImageFormat::RGB8 => 255,
+        ImageFormat::RGB16 => 65535,
+        _ => {
+            return Err(Error::Process {
+                process: "encode as PPM",
+                format: img.format,
+            })
+        }
+    };
+
+    let mut buf = format!("P6\n{} {}\n{maxval}\n", img.width, img.height).into_bytes();
+    write_raster(&mut buf, img);
+    Ok(Image::new(buf, ImageFormat::PPM, img.width, img.height))
+}
+
+/// Encode `img` as PAM (`P7`), which, unlike PPM, also supports an alpha channel.
+pub fn encode_pam(img: &Image) -> Result<Image, Error> {
+    let (depth, maxval, tuple_type) = match img.format {
+        ImageFormat::RGB8 => (3, 255, "RGB"),
+        ImageFormat::RGBA8 => (4, 255, "RGB_ALPHA"),
+        ImageFormat::RGB16 => (3, 65535, "RGB"),
+        ImageFormat::RGBA16 => (4, 65535, "RGB_ALPHA"),
+        _ => {
+            return Err(Error::Process {
+                process: "encode as PAM",
+                format: img.format,
+            })
+        }
+    };
+
+    let mut buf = format!(
+        "P7\nWIDTH {}\nHEIGHT {}\nDEPTH {depth}\nMAXVAL {maxval}\nTUPLTYPE {tuple_type}\nENDHDR\n",
+        img.width, img.height
+    )
+    .into_bytes();
+    write_raster(&mut buf, img);
+    Ok(Image::new(buf, ImageFormat::PAM, img.width, img.height))
+}
+
+/// Append the samples of `img` to `buf`, with 16-bit samples in big endian as Netpbm expects it.
+fn write_raster(buf: &mut Vec<u8>, img: &Image) {
+    match img.format {
+        ImageFormat::RGB16 | ImageFormat::RGBA16 => buf.extend(
+            samples16(img.as_ref())
+                .into_iter()
+                .flat_map(|v| v.to_be_bytes()),
+        ),
+        _ => buf.extend_from_slice(img.as_ref()),
+    }
+}
+
+struct Header {
+    width: u32,
+    height: u32,
+    /// Number of samples per pixel
+    depth: usize,
+    maxval: u16,
+    /// Offset of the raster within the data
+    offset: usize,
+}
+
+impl Header {
+    fn parse(data: &[u8]) -> Result<Self, PnmError> {
+        let mut tokens = Tokens { data, pos: 2 };
+        let header = match data.get(..2) {
+            Some(b"P5") | Some(b"P6") => {
+                let depth = if data[1] == b'5' { 1 } else { 3 };
+                let width = tokens.number()?;
+                let height = tokens.number()?;
+                let maxval = tokens.number()?;
+                // the raster starts after a single whitespace character
+                tokens.pos += 1;
+                Header {
+                    width,
+                    height,
+                    depth,
+                    maxval: u16::try_from(maxval).map_err(|_| PnmError::InvalidHeader)?,
+                    offset: tokens.pos,
+                }
+            }
+            Some(b"P7") => {
+                let (mut width, mut height, mut depth, mut maxval) = (None, None, None, None);
+                let mut tuple_type = Vec::new();
+                loop {
+                    match tokens.next().ok_or(PnmError::InvalidHeader)? {
+                        b"WIDTH" => width = Some(tokens.number()?),
+                        b"HEIGHT" => height = Some(tokens.number()?),
+                        b"DEPTH" => depth = Some(tokens.number()?),
+                        b"MAXVAL" => maxval = Some(tokens.number()?),
+                        b"TUPLTYPE" => tuple_type = tokens.line().to_vec(),
+                        b"ENDHDR" => break,
+                        _ => return Err(PnmError::InvalidHeader),
+                    }
+                }
+                // the raster starts after the newline following ENDHDR
+                tokens.pos += 1;
+
+                let depth = depth.ok_or(PnmError::InvalidHeader)?;
+                let expected = match tuple_type.as_slice() {
+                    b"GRAYSCALE" | b"BLACKANDWHITE" => 1,
+                    b"GRAYSCALE_ALPHA" | b"BLACKANDWHITE_ALPHA" => 2,
+                    b"RGB" => 3,
+                    b"RGB_ALPHA" => 4,
+                    // without a tuple type, fall back to the depth
+                    b"" if (1..=4).contains(&depth) => depth,
+                    _ => {
+                        return Err(PnmError::UnsupportedTupleType(
+                            String::from_utf8_lossy(&tuple_type).into_owned(),
+                        ))
+                    }
+                };
+                if depth != expected {
+                    return Err(PnmError::InvalidHeader);
+                }
+
+                Header {
+                    width: width.ok_or(PnmError::InvalidHeader)?,
+                    height: height.ok_or(PnmError::InvalidHeader)?,
+                    depth: depth as usize,
+                    maxval: maxval
+                        .and_then(|v| u16::try_from(v).ok())
+                        .ok_or(PnmError::InvalidHeader)?,
+                    offset: tokens.pos,
+                }
+            }
+            _ => return Err(PnmError::InvalidHeader),
+        };
+
+        if header.maxval == 0 {
+            return Err(PnmError::InvalidHeader);
+        }
+
+        Ok(header)
+    }
+}
+
+/// Whitespace separated tokens of a Netpbm header, skipping `#` comments.
+struct Tokens<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn next(&mut self) -> Option<&'a [u8]> {
+        loop {
+            match self.data.get(self.pos)? {
+                b'#' => {
+                    while !matches!(self.data.get(self.pos), Some(b'\n') | None) {
+                        self.pos += 1;
+                    }
+                }
+                c if c.is_ascii_whitespace() => self.pos += 1,
+                _ => break,
+            }
+        }
+
+        let start = self.pos;
+        while matches!(self.data.get(self.pos), Some(c) if !c.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+        Some(&self.data[start..self.pos])
+    }
+
+    fn number(&mut self) -> Result<u32, PnmError> {
+        self.next()
+            .and_then(|token| std::str::from_utf8(token).ok())
+            .and_then(|token| token.parse().ok())
+            .ok_or(PnmError::InvalidHeader)
+    }
+
+    /// The rest of the current line, with surrounding whitespace trimmed.
+    fn line(&mut self) -> &'a [u8] {
+        let start = self.pos;
+        while !matches!(self.data.get(self.pos), Some(b'\n') | None) {
+            self.pos += 1;
+        }
+        self.data[start..self.pos].trim_ascii()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PnmError {
+    #[error("invalid or unsupported Netpbm header")]
+    InvalidHeader,
+    #[error("unsupported PAM tuple type: {0}")]
+    UnsupportedTupleType(String),
+    #[error("Netpbm image data is truncated")]
+    Truncated,
+}
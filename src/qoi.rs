@@ -0,0 +1,62 @@
+use qoi::Channels;
+
+use crate::error::Error;
+use crate::{ColorType, Image, ImageFormat, ImageInfo};
+
+pub fn seed() -> u32 {
+    1
+}
+
+/// Decode a QOI image.
+pub fn decode(data: &[u8]) -> Result<Image, Error> {
+    let (header, buf) = qoi::decode_to_vec(data).map_err(QoiError::Decode)?;
+    let format = match header.channels {
+        Channels::Rgb => ImageFormat::RGB8,
+        Channels::Rgba => ImageFormat::RGBA8,
+    };
+
+    Ok(Image::new(buf, format, header.width, header.height))
+}
+
+/// Read the basic properties of a QOI image, by only reading its header.
+pub fn probe(data: &[u8]) -> Result<ImageInfo, Error> {
+    let header = qoi::decode_header(data).map_err(QoiError::Decode)?;
+    let has_alpha = header.channels == Channels::Rgba;
+
+    Ok(ImageInfo {
+        width: header.width,
+        height: header.height,
+        format: ImageFormat::QOI,
+        color_type: if has_alpha {
+            ColorType::RGBA
+        } else {
+            ColorType::RGB
+        },
+        bit_depth: 8,
+        has_alpha,
+        orientation: 1,
+    })
+}
+
+/// Encode `img` as QOI. 16-bit images are reduced to 8 bit, as QOI doesn't support more.
+pub fn encode(img: &Image) -> Result<Image, Error> {
+    let img = img.to_8bit();
+    if !matches!(img.format, ImageFormat::RGB8 | ImageFormat::RGBA8) {
+        return Err(Error::Process {
+            process: "encode as QOI",
+            format: img.format,
+        });
+    }
+
+    // the channel count is derived from the buffer size
+    let buf = qoi::encode_to_vec(img.as_ref(), img.width, img.height).map_err(QoiError::Encode)?;
+    Ok(Image::new(buf, ImageFormat::QOI, img.width, img.height))
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum QoiError {
+    #[error("failed to decode QOI image")]
+    Decode(#[source] qoi::Error),
+    #[error("failed to encode image as QOI")]
+    Encode(#[source] qoi::Error),
+}
@@ -1,19 +1,99 @@
 use std::borrow::Cow;
 
-use crate::crop::{crop, Fit};
+use crate::crop::{crop, crop_around, FocalPoint, Gravity};
 use crate::error::Error;
-use crate::{Image, ImageFormat, PixelFormat};
+use crate::{bytes16, samples16, Image, ImageFormat, PixelFormat};
 use rgb::FromSlice;
 
 pub fn seed() -> u32 {
     1
 }
 
+/// The resampling filter used to resize images.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Filter {
+    /// Nearest neighbor
+    Point = 1,
+    /// Bilinear
+    #[default]
+    Triangle = 2,
+    /// Catmull-Rom (bicubic), sharp when upscaling and downscaling
+    CatmullRom = 3,
+    /// Mitchell-Netravali (bicubic), slightly smoother than Catmull-Rom
+    Mitchell = 4,
+    /// Lanczos with a window of 3
+    Lanczos3 = 5,
+}
+
+impl TryFrom<u8> for Filter {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            1 => Filter::Point,
+            2 => Filter::Triangle,
+            3 => Filter::CatmullRom,
+            4 => Filter::Mitchell,
+            5 => Filter::Lanczos3,
+            _ => {
+                return Err(Error::InvalidValue {
+                    name: "filter",
+                    value,
+                })
+            }
+        })
+    }
+}
+
+impl From<Filter> for resize::Type {
+    fn from(filter: Filter) -> Self {
+        match filter {
+            Filter::Point => resize::Type::Point,
+            Filter::Triangle => resize::Type::Triangle,
+            Filter::CatmullRom => resize::Type::Catrom,
+            Filter::Mitchell => resize::Type::Mitchell,
+            Filter::Lanczos3 => resize::Type::Lanczos3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ResizeOptions {
+    /// Maintain the aspect ratio of the image, i.e. shrink one of the dimensions instead of
+    /// cropping the image if the aspect ratio changes.
+    pub maintain_aspect: bool,
+    /// Where to crop the image if the aspect ratio changes.
+    pub gravity: Gravity,
+    /// The point to keep in the center of the cropped image (as far as possible). Takes precedence
+    /// over `gravity`.
+    pub focal_point: Option<FocalPoint>,
+    /// The resampling filter.
+    pub filter: Filter,
+}
+
 pub fn resize(
+    img: &Image,
+    new_width: u32,
+    new_height: u32,
+    maintain_aspect: bool,
+) -> Result<Image, Error> {
+    resize_with_options(
+        img,
+        new_width,
+        new_height,
+        &ResizeOptions {
+            maintain_aspect,
+            ..Default::default()
+        },
+    )
+}
+
+pub fn resize_with_options(
     img: &Image,
     mut new_width: u32,
     mut new_height: u32,
-    maintain_aspect: bool,
+    opts: &ResizeOptions,
 ) -> Result<Image, Error> {
     // println!(
     //     "resize {} {} {} {}",
@@ -23,6 +103,8 @@ pub fn resize(
     let pixel_format = match img.format {
         ImageFormat::RGB8 => PixelFormat::RGB8,
         ImageFormat::RGBA8 => PixelFormat::RGBA8,
+        ImageFormat::RGB16 => PixelFormat::RGB16,
+        ImageFormat::RGBA16 => PixelFormat::RGBA16,
         _ => {
             return Err(Error::Process {
                 process: "resize",
@@ -46,7 +128,7 @@ pub fn resize(
     let aspect_after = f64::from(new_width) / f64::from(new_height);
 
     if (aspect_after - aspect_before).abs() >= f64::EPSILON {
-        if maintain_aspect {
+        if opts.maintain_aspect {
             if aspect_after > aspect_before {
                 let scale = f64::from(new_height) / f64::from(img.height);
                 new_width = (f64::from(img.width) * scale) as u32;
@@ -64,7 +146,10 @@ pub fn resize(
             } else {
                 ((f64::from(img.height) * aspect_after) as u32, img.height)
             };
-            img = Cow::Owned(crop(&img, crop_width, crop_height, Fit::Contain)?);
+            img = Cow::Owned(match opts.focal_point {
+                Some(focal_point) => crop_around(&img, crop_width, crop_height, focal_point)?,
+                None => crop(&img, crop_width, crop_height, opts.gravity)?,
+            });
         }
     }
 
@@ -82,7 +167,7 @@ pub fn resize(
                 new_width as usize,
                 new_height as usize,
                 resize::Pixel::RGB8,
-                resize::Type::Triangle,
+                opts.filter.into(),
             )?;
 
             let mut dst = vec![0u8; (new_width * new_height) as usize * pixel_format.pixel_size()];
@@ -96,14 +181,46 @@ pub fn resize(
                 new_width as usize,
                 new_height as usize,
                 resize::Pixel::RGBA8,
-                resize::Type::Triangle,
+                opts.filter.into(),
             )?;
 
             let mut dst = vec![0u8; (new_width * new_height) as usize * pixel_format.pixel_size()];
             resizer.resize(src.as_rgba(), dst.as_rgba_mut())?;
             dst
         }
+        PixelFormat::RGB16 => {
+            let mut resizer = resize::new(
+                img.width as usize,
+                img.height as usize,
+                new_width as usize,
+                new_height as usize,
+                resize::Pixel::RGB16,
+                opts.filter.into(),
+            )?;
+
+            let src = samples16(src);
+            let mut dst = vec![0u16; (new_width * new_height) as usize * 3];
+            resizer.resize(src.as_rgb(), dst.as_rgb_mut())?;
+            bytes16(&dst)
+        }
+        PixelFormat::RGBA16 => {
+            let mut resizer = resize::new(
+                img.width as usize,
+                img.height as usize,
+                new_width as usize,
+                new_height as usize,
+                resize::Pixel::RGBA16,
+                opts.filter.into(),
+            )?;
+
+            let src = samples16(src);
+            let mut dst = vec![0u16; (new_width * new_height) as usize * 4];
+            resizer.resize(src.as_rgba(), dst.as_rgba_mut())?;
+            bytes16(&dst)
+        }
     };
 
-    Ok(Image::new(dst, img.format, new_width, new_height))
+    Ok(Image::new(dst, img.format, new_width, new_height)
+        .with_icc_profile(img.icc_profile())
+        .with_metadata(img.metadata()))
 }
@@ -0,0 +1,217 @@
+use std::io::Cursor;
+
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::tags::Tag;
+use tiff::ColorType;
+
+use crate::error::Error;
+use crate::{bytes16, Image, ImageFormat, ImageInfo};
+
+#[derive(Debug, Clone, Default)]
+pub struct DecodeOptions {
+    /// Index of the page to decode, see [`pages`].
+    pub page: usize,
+    /// Decode 16-bit TIFFs as RGB16/RGBA16 instead of reducing them to 8 bit.
+    pub keep_16bit: bool,
+    /// Convert the image to sRGB, according to its ICC profile. For CMYK images, this also
+    /// replaces the naive conversion to RGB with one according to their (CMYK) profile. Requires
+    /// the `color` feature, decoding fails otherwise.
+    pub to_srgb: bool,
+}
+
+/// A page (image file directory) of a TIFF.
+#[derive(Debug, Clone, Copy)]
+pub struct Page {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Decode the first page of a TIFF.
+pub fn decode(data: &[u8]) -> Result<Image, Error> {
+    decode_with_options(data, &DecodeOptions::default())
+}
+
+pub fn decode_with_options(data: &[u8], opts: &DecodeOptions) -> Result<Image, Error> {
+    #[cfg(not(feature = "color"))]
+    if opts.to_srgb {
+        return Err(Error::ColorDisabled);
+    }
+
+    let mut decoder = Decoder::new(Cursor::new(data)).map_err(TiffError::from)?;
+    if opts.page > 0 {
+        decoder
+            .seek_to_image(opts.page)
+            .map_err(|_| TiffError::PageOutOfRange(opts.page))?;
+    }
+
+    let (width, height) = decoder.dimensions().map_err(TiffError::from)?;
+    let color_type = decoder.colortype().map_err(TiffError::from)?;
+    let channels = match color_type {
+        ColorType::Gray(8 | 16) => 1,
+        // grayscale with alpha is only reported as two samples
+        ColorType::Multiband {
+            bit_depth: 8 | 16,
+            num_samples: 2,
+        } => 2,
+        ColorType::RGB(8 | 16) => 3,
+        ColorType::RGBA(8 | 16) | ColorType::CMYK(8) => 4,
+        _ => return Err(TiffError::UnsupportedColorType(color_type).into()),
+    };
+
+    let icc_profile = decoder
+        .find_tag(Tag::IccProfile)
+        .map_err(TiffError::from)?
+        .and_then(|value| value.into_u8_vec().ok());
+
+    let has_alpha = channels == 2 || matches!(color_type, ColorType::RGBA(_));
+    let is_cmyk = matches!(color_type, ColorType::CMYK(_));
+    let mut img = match decoder.read_image().map_err(TiffError::from)? {
+        DecodingResult::U8(buf) if is_cmyk => Image::new(
+            cmyk_to_rgb(&buf, icc_profile.as_deref(), opts.to_srgb)?,
+            ImageFormat::RGB8,
+            width,
+            height,
+        ),
+        DecodingResult::U8(buf) => Image::new(
+            expand_gray(buf, channels),
+            if has_alpha {
+                ImageFormat::RGBA8
+            } else {
+                ImageFormat::RGB8
+            },
+            width,
+            height,
+        ),
+        DecodingResult::U16(buf) => {
+            let img = Image::new(
+                bytes16(&expand_gray(buf, channels)),
+                if has_alpha {
+                    ImageFormat::RGBA16
+                } else {
+                    ImageFormat::RGB16
+                },
+                width,
+                height,
+            );
+            if opts.keep_16bit {
+                img
+            } else {
+                img.to_8bit().into_owned()
+            }
+        }
+        _ => return Err(TiffError::UnsupportedColorType(color_type).into()),
+    };
+    // the profile of a CMYK TIFF doesn't apply to the converted RGB pixels
+    if !is_cmyk {
+        img.set_icc_profile(icc_profile.filter(|icc| crate::is_rgb_icc_profile(icc)));
+    }
+
+    #[cfg(feature = "color")]
+    if opts.to_srgb {
+        return crate::color::to_srgb(img);
+    }
+
+    Ok(img)
+}
+
+/// Read the basic properties of the first page of a TIFF, by only reading its image file
+/// directory.
+pub fn probe(data: &[u8]) -> Result<ImageInfo, Error> {
+    let mut decoder = Decoder::new(Cursor::new(data)).map_err(TiffError::from)?;
+    let (width, height) = decoder.dimensions().map_err(TiffError::from)?;
+    let color_type = decoder.colortype().map_err(TiffError::from)?;
+    let orientation = decoder
+        .find_tag_unsigned(Tag::Orientation)
+        .map_err(TiffError::from)?;
+
+    let (info_color_type, bit_depth) = match color_type {
+        ColorType::Gray(bit_depth) => (crate::ColorType::Gray, bit_depth),
+        ColorType::GrayA(bit_depth)
+        | ColorType::Multiband {
+            bit_depth,
+            num_samples: 2,
+        } => (crate::ColorType::GrayAlpha, bit_depth),
+        ColorType::RGB(bit_depth) | ColorType::YCbCr(bit_depth) | ColorType::Lab(bit_depth) => {
+            (crate::ColorType::RGB, bit_depth)
+        }
+        ColorType::RGBA(bit_depth) => (crate::ColorType::RGBA, bit_depth),
+        ColorType::Palette(bit_depth) => (crate::ColorType::Indexed, bit_depth),
+        ColorType::CMYK(bit_depth) | ColorType::CMYKA(bit_depth) => {
+            (crate::ColorType::CMYK, bit_depth)
+        }
+        _ => return Err(TiffError::UnsupportedColorType(color_type).into()),
+    };
+
+    Ok(ImageInfo {
+        width,
+        height,
+        format: ImageFormat::TIFF,
+        color_type: info_color_type,
+        bit_depth,
+        has_alpha: matches!(
+            info_color_type,
+            crate::ColorType::GrayAlpha | crate::ColorType::RGBA
+        ) || matches!(color_type, ColorType::CMYKA(_)),
+        orientation: crate::info_orientation(orientation),
+    })
+}
+
+/// List the pages of a TIFF.
+pub fn pages(data: &[u8]) -> Result<Vec<Page>, Error> {
+    let mut decoder = Decoder::new(Cursor::new(data)).map_err(TiffError::from)?;
+    let mut pages = Vec::new();
+    loop {
+        let (width, height) = decoder.dimensions().map_err(TiffError::from)?;
+        pages.push(Page { width, height });
+        if !decoder.more_images() {
+            break;
+        }
+        decoder.next_image().map_err(TiffError::from)?;
+    }
+
+    Ok(pages)
+}
+
+/// Expand grayscale to RGB, so that all further processing only needs to support RGB(A).
+fn expand_gray<T: Copy>(buf: Vec<T>, channels: usize) -> Vec<T> {
+    match channels {
+        1 => buf.iter().flat_map(|&v| [v, v, v]).collect(),
+        2 => buf
+            .chunks_exact(2)
+            .flat_map(|px| [px[0], px[0], px[0], px[1]])
+            .collect(),
+        _ => buf,
+    }
+}
+
+/// Convert CMYK samples to RGB8, according to the CMYK `icc_profile` if the conversion to sRGB is
+/// requested, or naively otherwise.
+#[cfg_attr(not(feature = "color"), allow(unused_variables))]
+fn cmyk_to_rgb(buf: &[u8], icc_profile: Option<&[u8]>, to_srgb: bool) -> Result<Vec<u8>, Error> {
+    #[cfg(feature = "color")]
+    if let Some(transform) = match icc_profile {
+        Some(icc_profile) if to_srgb => crate::color::cmyk_to_srgb(icc_profile)?,
+        _ => None,
+    } {
+        let mut rgb = vec![0; buf.len() / 4 * 3];
+        transform
+            .transform(buf, &mut rgb)
+            .map_err(crate::color::ColorError::Transform)?;
+        return Ok(rgb);
+    }
+
+    Ok(buf
+        .chunks_exact(4)
+        .flat_map(|px| crate::cmyk_to_rgb([px[0], px[1], px[2], px[3]]))
+        .collect())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TiffError {
+    #[error("failed to decode TIFF image")]
+    Decode(#[from] tiff::TiffError),
+    #[error("unsupported color type: {0:?}")]
+    UnsupportedColorType(ColorType),
+    #[error("TIFF does not contain a page {0}")]
+    PageOutOfRange(usize),
+}
@@ -0,0 +1,198 @@
+//! Rotating and flipping images.
+
+use crate::error::Error;
+use crate::{exif, metadata, Image, ImageFormat, PixelFormat};
+
+/// A rotation or flip of an image.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    /// Rotate by 90deg clockwise.
+    Rotate90 = 1,
+    /// Rotate by 180deg.
+    Rotate180 = 2,
+    /// Rotate by 270deg clockwise, i.e. by 90deg counterclockwise.
+    Rotate270 = 3,
+    /// Mirror the columns, i.e. swap left and right.
+    FlipHorizontal = 4,
+    /// Mirror the rows, i.e. swap top and bottom.
+    FlipVertical = 5,
+    /// Mirror along the top-left to bottom-right diagonal.
+    Transpose = 6,
+    /// Mirror along the top-right to bottom-left diagonal.
+    Transverse = 7,
+}
+
+impl TryFrom<u8> for Transform {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            1 => Transform::Rotate90,
+            2 => Transform::Rotate180,
+            3 => Transform::Rotate270,
+            4 => Transform::FlipHorizontal,
+            5 => Transform::FlipVertical,
+            6 => Transform::Transpose,
+            7 => Transform::Transverse,
+            _ => {
+                return Err(Error::InvalidValue {
+                    name: "transform",
+                    value,
+                })
+            }
+        })
+    }
+}
+
+impl Transform {
+    /// The transformation required to display an image upright, as specified by the Exif
+    /// orientation tag. `None` if the image already is upright (or the orientation is invalid).
+    pub fn from_exif(orientation: u16) -> Option<Self> {
+        match orientation {
+            2 => Some(Transform::FlipHorizontal),
+            3 => Some(Transform::Rotate180),
+            4 => Some(Transform::FlipVertical),
+            5 => Some(Transform::Transpose),
+            6 => Some(Transform::Rotate90),
+            7 => Some(Transform::Transverse),
+            8 => Some(Transform::Rotate270),
+            _ => None,
+        }
+    }
+
+    /// Whether rows become columns (and width and height are swapped).
+    pub fn transposes(self) -> bool {
+        matches!(
+            self,
+            Transform::Transpose
+                | Transform::Rotate90
+                | Transform::Transverse
+                | Transform::Rotate270
+        )
+    }
+
+    /// Whether the pixels of each source row have to be mirrored.
+    pub(crate) fn mirrors_rows(self) -> bool {
+        matches!(
+            self,
+            Transform::FlipHorizontal
+                | Transform::Rotate180
+                | Transform::Transverse
+                | Transform::Rotate270
+        )
+    }
+
+    /// Whether the source rows are placed in reverse order, i.e. from the bottom up (or from the
+    /// right to the left, if transposed).
+    pub(crate) fn reverses_rows(self) -> bool {
+        matches!(
+            self,
+            Transform::Rotate180
+                | Transform::FlipVertical
+                | Transform::Rotate90
+                | Transform::Transverse
+        )
+    }
+}
+
+/// Rotate or flip the image. The orientation of its Exif and XMP metadata is reset to 1, as viewers
+/// would otherwise apply it on top of the transformation.
+pub fn transform(img: &Image, transform: Transform) -> Result<Image, Error> {
+    let pixel_format = match img.format {
+        ImageFormat::RGB8 => PixelFormat::RGB8,
+        ImageFormat::RGBA8 => PixelFormat::RGBA8,
+        ImageFormat::RGB16 => PixelFormat::RGB16,
+        ImageFormat::RGBA16 => PixelFormat::RGBA16,
+        _ => {
+            return Err(Error::Process {
+                process: "transform",
+                format: img.format,
+            })
+        }
+    };
+
+    let pixel_size = pixel_format.pixel_size();
+    let (width, height) = (img.width as usize, img.height as usize);
+    let row_stride = width * pixel_size;
+    let src: &[u8] = img.as_ref();
+    let src = &src[..row_stride * height];
+    let mut dst = vec![0u8; src.len()];
+
+    // an empty image has no rows to transform
+    let rows = src.chunks_exact(row_stride.max(1)).enumerate();
+    let mirrors_rows = transform.mirrors_rows();
+    let reverses_rows = transform.reverses_rows();
+    let (new_width, new_height) = if transform.transposes() {
+        // same as when decoding rotated JPEGs: each row becomes a column
+        for (y_from, row) in rows {
+            let x_to = if reverses_rows {
+                height - 1 - y_from
+            } else {
+                y_from
+            };
+            for (x_from, pixel) in row.chunks_exact(pixel_size).enumerate() {
+                let y_to = if mirrors_rows {
+                    width - 1 - x_from
+                } else {
+                    x_from
+                };
+                let to = (x_to + y_to * height) * pixel_size;
+                dst[to..(to + pixel_size)].copy_from_slice(pixel);
+            }
+        }
+        (img.height, img.width)
+    } else {
+        for (y_from, row) in rows {
+            let y_to = if reverses_rows {
+                height - 1 - y_from
+            } else {
+                y_from
+            };
+            let offset = y_to * row_stride;
+            let row_to = &mut dst[offset..offset + row_stride];
+            row_to.copy_from_slice(row);
+            if mirrors_rows {
+                mirror(row_to, pixel_size);
+            }
+        }
+        (img.width, img.height)
+    };
+
+    let metadata = img.metadata().map(|metadata| {
+        let mut metadata = metadata.clone();
+        if let Some(exif) = &mut metadata.exif {
+            exif::reset_orientation(exif);
+        }
+        metadata.xmp = metadata.xmp.as_deref().map(metadata::reset_xmp_orientation);
+        metadata
+    });
+
+    Ok(Image::new(dst, img.format, new_width, new_height)
+        .with_icc_profile(img.icc_profile())
+        .with_metadata(metadata.as_ref()))
+}
+
+/// Mirror the pixels of `row` in place.
+///
+/// # Panics
+///
+/// Panics if `row.len()` is not a multiple of `pixel_size`.
+pub(crate) fn mirror(row: &mut [u8], pixel_size: usize) {
+    assert_eq!(
+        row.len() % pixel_size,
+        0,
+        "row length must be a multiple of the pixel size"
+    );
+
+    let pixel_count = row.len() / pixel_size;
+    let (left, right) = row.split_at_mut(pixel_count / 2 * pixel_size);
+    // skip the middle pixel of rows with an odd number of pixels
+    let right = &mut right[(pixel_count % 2) * pixel_size..];
+    for (a, b) in left
+        .chunks_exact_mut(pixel_size)
+        .zip(right.chunks_exact_mut(pixel_size).rev())
+    {
+        a.swap_with_slice(b);
+    }
+}
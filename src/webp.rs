@@ -1,65 +1,156 @@
+use std::ffi::CStr;
 use std::os::raw::c_int;
 
 use libwebp_sys::{
-    WebPConfig, WebPConfigPreset, WebPEncode, WebPMemoryWrite, WebPMemoryWriter,
-    WebPMemoryWriterClear, WebPMemoryWriterInit, WebPPicture, WebPPictureFree,
-    WebPPictureImportRGB, WebPPictureImportRGBA, WebPPictureInit, WebPValidateConfig,
-    WEBP_PRESET_PHOTO,
+    WebPAnimEncoderAdd, WebPAnimEncoderAssemble, WebPAnimEncoderDelete, WebPAnimEncoderNew,
+    WebPAnimEncoderOptions, WebPAnimEncoderOptionsInit, WebPBitstreamFeatures, WebPConfig,
+    WebPConfigPreset, WebPData, WebPDataClear, WebPDataInit, WebPDecodeRGBAInto, WebPDecodeRGBInto,
+    WebPEncode, WebPGetFeatures, WebPMemoryWrite, WebPMemoryWriter, WebPMemoryWriterClear,
+    WebPMemoryWriterInit, WebPMuxAssemble, WebPMuxDelete, WebPMuxNew, WebPMuxSetChunk,
+    WebPMuxSetImage, WebPPicture, WebPPictureFree, WebPPictureImportRGB, WebPPictureImportRGBA,
+    WebPPictureInit, WebPPreset, WebPValidateConfig, VP8_STATUS_OK, WEBP_MUX_OK,
+    WEBP_PRESET_DRAWING, WEBP_PRESET_ICON, WEBP_PRESET_PHOTO, WEBP_PRESET_PICTURE,
+    WEBP_PRESET_TEXT,
 };
 
+use crate::animation::Animation;
 use crate::error::Error;
-use crate::{Image, ImageFormat};
+use crate::{ColorType, Image, ImageFormat, ImageInfo};
 
 pub fn seed() -> u32 {
     0
 }
 
+pub fn decode(data: &[u8]) -> Result<Image, Error> {
+    unsafe {
+        let mut features: WebPBitstreamFeatures = std::mem::zeroed();
+        if WebPGetFeatures(data.as_ptr(), data.len(), &mut features) != VP8_STATUS_OK {
+            return Err(Error::Webp("failed to read bitstream features"));
+        }
+        if features.has_animation != 0 {
+            return Err(Error::Webp("animated WebPs are not supported"));
+        }
+
+        let width = features.width as u32;
+        let height = features.height as u32;
+        let (format, pixel_size) = if features.has_alpha != 0 {
+            (ImageFormat::RGBA8, 4)
+        } else {
+            (ImageFormat::RGB8, 3)
+        };
+
+        let row_stride = width as usize * pixel_size;
+        let mut buffer = vec![0u8; row_stride * height as usize];
+        let decode_into = if features.has_alpha != 0 {
+            WebPDecodeRGBAInto
+        } else {
+            WebPDecodeRGBInto
+        };
+        let out = decode_into(
+            data.as_ptr(),
+            data.len(),
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            row_stride as c_int,
+        );
+        if out.is_null() {
+            return Err(Error::Webp("failed to decode"));
+        }
+
+        Ok(Image::new(buffer, format, width, height))
+    }
+}
+
+/// Read the basic properties of a WebP, by only parsing its RIFF chunk headers.
+pub fn probe(data: &[u8]) -> Result<ImageInfo, Error> {
+    let mut features: WebPBitstreamFeatures = unsafe { std::mem::zeroed() };
+    if unsafe { WebPGetFeatures(data.as_ptr(), data.len(), &mut features) } != VP8_STATUS_OK {
+        return Err(Error::Webp("failed to read bitstream features"));
+    }
+
+    let orientation = chunk(data, b"EXIF").and_then(|exif| {
+        // some encoders wrongly include the header used in JPEGs
+        crate::exif::tiff_orientation(exif.strip_prefix(b"Exif\0\0").unwrap_or(exif))
+    });
+    let has_alpha = features.has_alpha != 0;
+    Ok(ImageInfo {
+        width: features.width as u32,
+        height: features.height as u32,
+        format: ImageFormat::WEBP,
+        color_type: if has_alpha {
+            ColorType::RGBA
+        } else {
+            ColorType::RGB
+        },
+        bit_depth: 8,
+        has_alpha,
+        orientation: crate::info_orientation(orientation),
+    })
+}
+
+/// The payload of the first chunk of type `fourcc` of the RIFF container `webp`.
+fn chunk<'a>(webp: &'a [u8], fourcc: &[u8; 4]) -> Option<&'a [u8]> {
+    // chunks follow the RIFF header and are padded to an even size
+    let mut pos = 12;
+    while let Some(header) = webp.get(pos..pos + 8) {
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let payload = webp.get(pos + 8..(pos + 8).checked_add(size)?)?;
+        if &header[..4] == fourcc {
+            return Some(payload);
+        }
+        pos += 8 + size + size % 2;
+    }
+    None
+}
+
 #[derive(Debug, Clone)]
 pub struct EncodeOptions {
-    /// 0-100 scale
+    /// 0-100 scale; for lossless encoding, this is the compression effort
     pub quality: u16,
+    /// Encode without any loss of pixel data.
+    pub lossless: bool,
+    /// 0-100 scale, with 100 turning near-lossless off; lower values preprocess the image for
+    /// better compression. Setting it below 100 implies `lossless`.
+    pub near_lossless: u8,
+    /// Preserve the RGB values under fully transparent pixels.
+    pub exact: bool,
+    /// 0 (fast) - 6 (slow but better compression)
+    pub method: u8,
+    /// Tune the encoder for the kind of image that is encoded.
+    pub preset: Preset,
+    /// 0-100 scale
+    pub alpha_quality: u16,
+    /// Use the slower but more accurate RGB to YUV conversion for lossy encoding.
+    pub use_sharp_yuv: bool,
+}
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// Digital picture, like portrait, inner shot
+    Picture = 1,
+    /// Outdoor photograph, with natural lighting
+    Photo = 2,
+    /// Hand or line drawing, with high-contrast details
+    Drawing = 3,
+    /// Small-sized colorful images
+    Icon = 4,
+    /// Text-like
+    Text = 5,
 }
 
 pub fn encode(img: &Image, opts: &EncodeOptions) -> Result<Image, Error> {
     unsafe {
-        let mut config: WebPConfig = std::mem::zeroed();
-        if WebPConfigPreset(&mut config, WEBP_PRESET_PHOTO, opts.quality as f32) == 0 {
-            return Err(Error::Webp("failed to initialize config preset"));
-        }
-        if WebPValidateConfig(&config) == 0 {
-            return Err(Error::Webp("invalid config"));
-        }
-
-        let mut picture: WebPPicture = std::mem::zeroed();
-        if WebPPictureInit(&mut picture) == 0 {
-            return Err(Error::Webp("failed to initialize picture"));
-        }
-        picture.width = img.width as i32;
-        picture.height = img.height as i32;
+        let config = config(opts)?;
 
+        // lossless encoding and sharp YUV conversion both require ARGB input
+        let use_argb = config.lossless != 0 || config.use_sharp_yuv != 0;
+        let mut picture = picture(img, use_argb)?;
         let mut writer: WebPMemoryWriter = std::mem::zeroed();
         WebPMemoryWriterInit(&mut writer);
         picture.writer = Some(write);
         picture.custom_ptr = (&mut writer) as *mut WebPMemoryWriter as *mut _;
 
-        let ok = match img.format {
-            ImageFormat::RGB8 => {
-                WebPPictureImportRGB(&mut picture, img.as_ref().as_ptr(), (img.width * 3) as i32)
-            }
-            ImageFormat::RGBA8 => {
-                WebPPictureImportRGBA(&mut picture, img.as_ref().as_ptr(), (img.width * 4) as i32)
-            }
-            _ => {
-                return Err(Error::Process {
-                    process: "encode as WebP",
-                    format: img.format,
-                })
-            }
-        };
-        if ok == 0 {
-            return Err(Error::Webp("failed to import pixel data"));
-        }
-
         let ok = WebPEncode(&config, &mut picture);
         WebPPictureFree(&mut picture);
         if ok == 0 {
@@ -68,13 +159,227 @@ pub fn encode(img: &Image, opts: &EncodeOptions) -> Result<Image, Error> {
         }
 
         let data = Vec::from_raw_parts(writer.mem, writer.size, writer.max_size);
+        let metadata = img.metadata();
+        let chunks: Vec<(&CStr, &[u8])> = [
+            (c"ICCP", img.icc_profile()),
+            (c"EXIF", metadata.and_then(|m| m.exif.as_deref())),
+            (
+                c"XMP ",
+                metadata.and_then(|m| m.xmp.as_deref()).map(str::as_bytes),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(fourcc, payload)| Some((fourcc, payload?)))
+        .collect();
+        let data = if chunks.is_empty() {
+            data
+        } else {
+            embed_chunks(&data, &chunks)?
+        };
         Ok(Image::new(data, ImageFormat::WEBP, img.width, img.height))
     }
 }
 
+/// Encode all frames of `anim` into an animated WebP. Every frame must have the dimensions of the
+/// animation.
+pub fn encode_animation(anim: &Animation, opts: &EncodeOptions) -> Result<Image, Error> {
+    unsafe {
+        let config = config(opts)?;
+
+        let mut enc_options: WebPAnimEncoderOptions = std::mem::zeroed();
+        if WebPAnimEncoderOptionsInit(&mut enc_options) == 0 {
+            return Err(Error::Webp("failed to initialize animation options"));
+        }
+        enc_options.anim_params.loop_count = anim.loop_count() as c_int;
+
+        let enc = WebPAnimEncoderNew(anim.width() as c_int, anim.height() as c_int, &enc_options);
+        if enc.is_null() {
+            return Err(Error::Webp("failed to initialize animation encoder"));
+        }
+
+        let mut timestamp = 0;
+        for frame in anim.frames() {
+            let img = frame.image();
+            if img.width != anim.width() || img.height != anim.height() {
+                WebPAnimEncoderDelete(enc);
+                return Err(Error::Webp("frame dimensions do not match animation"));
+            }
+
+            // the animation encoder works on ARGB, so importing as YUV would only add a conversion
+            let mut picture = match picture(img, true) {
+                Ok(picture) => picture,
+                Err(err) => {
+                    WebPAnimEncoderDelete(enc);
+                    return Err(err);
+                }
+            };
+            let ok = WebPAnimEncoderAdd(enc, &mut picture, timestamp, &config);
+            WebPPictureFree(&mut picture);
+            if ok == 0 {
+                WebPAnimEncoderDelete(enc);
+                return Err(Error::Webp("failed to add animation frame"));
+            }
+            timestamp += frame.delay() as c_int;
+        }
+
+        // flush with the end timestamp, which determines the duration of the last frame
+        if WebPAnimEncoderAdd(enc, std::ptr::null_mut(), timestamp, std::ptr::null()) == 0 {
+            WebPAnimEncoderDelete(enc);
+            return Err(Error::Webp("failed to add animation frame"));
+        }
+
+        let mut webp_data: WebPData = std::mem::zeroed();
+        WebPDataInit(&mut webp_data);
+        let ok = WebPAnimEncoderAssemble(enc, &mut webp_data);
+        WebPAnimEncoderDelete(enc);
+        if ok == 0 {
+            WebPDataClear(&mut webp_data);
+            return Err(Error::Webp("failed to assemble animation"));
+        }
+
+        let data = std::slice::from_raw_parts(webp_data.bytes, webp_data.size).to_vec();
+        WebPDataClear(&mut webp_data);
+        Ok(Image::new(
+            data,
+            ImageFormat::WEBP,
+            anim.width(),
+            anim.height(),
+        ))
+    }
+}
+
+/// Re-mux the encoded `webp` into an extended WebP with additional `chunks` (e.g. the ICC profile
+/// as `ICCP` chunk), given as FourCC and payload.
+unsafe fn embed_chunks(webp: &[u8], chunks: &[(&CStr, &[u8])]) -> Result<Vec<u8>, Error> {
+    let mux = WebPMuxNew();
+    if mux.is_null() {
+        return Err(Error::Webp("failed to initialize muxer"));
+    }
+
+    let image = WebPData {
+        bytes: webp.as_ptr(),
+        size: webp.len(),
+    };
+    let mut webp_data: WebPData = std::mem::zeroed();
+    WebPDataInit(&mut webp_data);
+    let ok = WebPMuxSetImage(mux, &image, 0) == WEBP_MUX_OK
+        && chunks.iter().all(|(fourcc, payload)| {
+            let chunk = WebPData {
+                bytes: payload.as_ptr(),
+                size: payload.len(),
+            };
+            WebPMuxSetChunk(mux, fourcc.as_ptr(), &chunk, 0) == WEBP_MUX_OK
+        })
+        && WebPMuxAssemble(mux, &mut webp_data) == WEBP_MUX_OK;
+    WebPMuxDelete(mux);
+    if !ok {
+        WebPDataClear(&mut webp_data);
+        return Err(Error::Webp("failed to embed ICC profile or metadata"));
+    }
+
+    let data = std::slice::from_raw_parts(webp_data.bytes, webp_data.size).to_vec();
+    WebPDataClear(&mut webp_data);
+    Ok(data)
+}
+
+unsafe fn config(opts: &EncodeOptions) -> Result<WebPConfig, Error> {
+    let mut config: WebPConfig = std::mem::zeroed();
+    if WebPConfigPreset(&mut config, opts.preset.into(), opts.quality as f32) == 0 {
+        return Err(Error::Webp("failed to initialize config preset"));
+    }
+    config.lossless = c_int::from(opts.lossless || opts.near_lossless < 100);
+    config.near_lossless = c_int::from(opts.near_lossless.min(100));
+    config.exact = c_int::from(opts.exact);
+    config.method = c_int::from(opts.method);
+    config.alpha_quality = c_int::from(opts.alpha_quality);
+    config.use_sharp_yuv = c_int::from(opts.use_sharp_yuv);
+    if WebPValidateConfig(&config) == 0 {
+        return Err(Error::Webp("invalid config"));
+    }
+    Ok(config)
+}
+
+/// Create a picture from `img`, stored as ARGB if `use_argb` is set and as YUV(A) otherwise. The
+/// picture must be freed with `WebPPictureFree`.
+unsafe fn picture(img: &Image, use_argb: bool) -> Result<WebPPicture, Error> {
+    // WebP only supports 8-bit samples
+    let img = img.to_8bit();
+    let img: &Image = &img;
+
+    let mut picture: WebPPicture = std::mem::zeroed();
+    if WebPPictureInit(&mut picture) == 0 {
+        return Err(Error::Webp("failed to initialize picture"));
+    }
+    picture.width = img.width as i32;
+    picture.height = img.height as i32;
+    picture.use_argb = c_int::from(use_argb);
+
+    let ok = match img.format {
+        ImageFormat::RGB8 => {
+            WebPPictureImportRGB(&mut picture, img.as_ref().as_ptr(), (img.width * 3) as i32)
+        }
+        ImageFormat::RGBA8 => {
+            WebPPictureImportRGBA(&mut picture, img.as_ref().as_ptr(), (img.width * 4) as i32)
+        }
+        _ => {
+            return Err(Error::Process {
+                process: "encode as WebP",
+                format: img.format,
+            })
+        }
+    };
+    if ok == 0 {
+        WebPPictureFree(&mut picture);
+        return Err(Error::Webp("failed to import pixel data"));
+    }
+
+    Ok(picture)
+}
+
 impl Default for EncodeOptions {
     fn default() -> Self {
-        Self { quality: 80 }
+        Self {
+            quality: 80,
+            lossless: false,
+            near_lossless: 100,
+            exact: false,
+            method: 4,
+            preset: Preset::Photo,
+            alpha_quality: 100,
+            use_sharp_yuv: false,
+        }
+    }
+}
+
+impl TryFrom<u8> for Preset {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            1 => Preset::Picture,
+            2 => Preset::Photo,
+            3 => Preset::Drawing,
+            4 => Preset::Icon,
+            5 => Preset::Text,
+            _ => {
+                return Err(Error::InvalidValue {
+                    name: "WebP preset",
+                    value,
+                })
+            }
+        })
+    }
+}
+
+impl From<Preset> for WebPPreset {
+    fn from(preset: Preset) -> Self {
+        match preset {
+            Preset::Picture => WEBP_PRESET_PICTURE,
+            Preset::Photo => WEBP_PRESET_PHOTO,
+            Preset::Drawing => WEBP_PRESET_DRAWING,
+            Preset::Icon => WEBP_PRESET_ICON,
+            Preset::Text => WEBP_PRESET_TEXT,
+        }
     }
 }
 
@@ -0,0 +1,260 @@
+use wimg::avif::EncodeOptions;
+use wimg::metadata::Metadata;
+use wimg::{Image, ImageFormat};
+
+const OPTS: EncodeOptions = EncodeOptions {
+    quality: 100,
+    speed: 10,
+};
+
+/// A 16x16 image with a horizontal red and a vertical blue gradient.
+fn pixels(channels: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(16 * 16 * channels);
+    for y in 0..16u8 {
+        for x in 0..16u8 {
+            data.extend_from_slice(&[x * 16, 128, y * 16, 255 - x * 8][..channels]);
+        }
+    }
+    data
+}
+
+fn assert_close(actual: &[u8], expected: &[u8]) {
+    assert_eq!(actual.len(), expected.len());
+    for (i, (a, e)) in actual.iter().zip(expected).enumerate() {
+        assert!(a.abs_diff(*e) <= 8, "sample {i}: {a} != {e}");
+    }
+}
+
+#[test]
+fn decodes_8bit() {
+    // wimg encodes 8-bit input as 10-bit AVIF, so encode with ravif directly
+    let data = pixels(3);
+    let avif = ravif::Encoder::new()
+        .with_quality(100.0)
+        .with_speed(10)
+        .with_bit_depth(ravif::BitDepth::Eight)
+        .with_internal_color_model(ravif::ColorModel::YCbCr)
+        .encode_rgb(ravif::Img::new(rgb::FromSlice::as_rgb(&data[..]), 16, 16))
+        .unwrap()
+        .avif_file;
+
+    let info = wimg::avif::probe(&avif).unwrap();
+    assert_eq!(info.bit_depth, 8);
+    assert!(!info.has_alpha);
+
+    let decoded = wimg::avif::decode(&avif).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (16, 16));
+    assert_close(decoded.as_ref(), &pixels(3));
+}
+
+#[test]
+fn decodes_10bit() {
+    // widen the 8-bit samples to 16 bit
+    let data = pixels(3)
+        .into_iter()
+        .flat_map(|v| (u16::from(v) * 257).to_ne_bytes())
+        .collect();
+    let img = Image::new(data, ImageFormat::RGB16, 16, 16);
+    let avif = wimg::avif::encode(&img, &OPTS).unwrap();
+
+    let info = wimg::avif::probe(avif.as_ref()).unwrap();
+    assert_eq!(info.bit_depth, 10);
+
+    let decoded = wimg::avif::decode(avif.as_ref()).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (16, 16));
+    assert_close(decoded.as_ref(), &pixels(3));
+}
+
+#[test]
+fn decodes_alpha() {
+    let img = Image::new(pixels(4), ImageFormat::RGBA8, 16, 16);
+    let avif = wimg::avif::encode(&img, &OPTS).unwrap();
+
+    let info = wimg::avif::probe(avif.as_ref()).unwrap();
+    assert!(info.has_alpha);
+
+    let decoded = wimg::avif::decode(avif.as_ref()).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (16, 16));
+    assert_close(decoded.as_ref(), &pixels(4));
+}
+
+/// Not a valid profile, but encoders embed profiles verbatim.
+const ICC_PROFILE: &[u8] =
+    b"\0\0\0\x30appl\x02\x10\0\0mntrRGB XYZ \x07\xe8\0\x01\0\x01\0\0\0\0\0\0";
+
+fn embeds_icc_profile(format: ImageFormat, channels: usize) {
+    let mut img = Image::new(pixels(channels), format, 16, 16);
+    let plain = wimg::avif::encode(&img, &OPTS).unwrap();
+    img.set_icc_profile(Some(ICC_PROFILE.to_vec()));
+    let avif = wimg::avif::encode(&img, &OPTS).unwrap();
+
+    // the image data is unchanged, just moved behind the grown `meta` box
+    let expected = avif_parse::read_avif(&mut plain.as_ref()).unwrap();
+    let parsed = avif_parse::read_avif(&mut avif.as_ref()).unwrap();
+    assert_eq!(parsed.primary_item, expected.primary_item);
+    assert_eq!(parsed.alpha_item, expected.alpha_item);
+    assert_eq!(parsed.alpha_item.is_some(), channels == 4);
+
+    let decoded = wimg::avif::decode(avif.as_ref()).unwrap();
+    assert_eq!(decoded.icc_profile(), Some(ICC_PROFILE));
+    assert_close(decoded.as_ref(), &pixels(channels));
+    assert_eq!(
+        wimg::avif::decode(plain.as_ref()).unwrap().icc_profile(),
+        None
+    );
+}
+
+#[test]
+fn embeds_icc_profile_without_alpha() {
+    embeds_icc_profile(ImageFormat::RGB8, 3);
+}
+
+#[test]
+fn embeds_icc_profile_with_alpha() {
+    embeds_icc_profile(ImageFormat::RGBA8, 4);
+}
+
+/// A minimal TIFF structure, with an empty IFD0.
+const EXIF: &[u8] = b"MM\0\x2a\0\0\0\x08\0\0\0\0\0\0";
+const XMP: &str = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"/>"#;
+
+fn find_box(data: &[u8], fourcc: &[u8; 4]) -> Option<std::ops::Range<usize>> {
+    let mut pos = 0;
+    while pos + 8 <= data.len() {
+        let size = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+        if &data[pos + 4..pos + 8] == fourcc {
+            return Some(pos..pos + size);
+        }
+        pos += size;
+    }
+    None
+}
+
+fn u16_at(data: &[u8], pos: usize) -> usize {
+    u16::from_be_bytes(data[pos..pos + 2].try_into().unwrap()).into()
+}
+
+fn u32_at(data: &[u8], pos: usize) -> usize {
+    u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize
+}
+
+/// The data of the item of type `item_type` and the items it describes (`cdsc` references), by
+/// parsing `iinf`, `iloc` and `iref` as written by ravif (all version 0, with 32-bit offsets).
+fn item(avif: &[u8], item_type: &[u8; 4]) -> Option<(Vec<u8>, Vec<usize>)> {
+    let meta = find_box(avif, b"meta").unwrap();
+    let meta = &avif[meta.start + 12..meta.end];
+
+    let iinf = &meta[find_box(meta, b"iinf").unwrap()];
+    let mut infes = &iinf[14..];
+    let mut id = None;
+    for _ in 0..u16_at(iinf, 12) {
+        let infe = &infes[..u32_at(infes, 0)];
+        if &infe[16..20] == item_type {
+            id = Some(u16_at(infe, 12));
+        }
+        infes = &infes[infe.len()..];
+    }
+    let id = id?;
+
+    let iloc = &meta[find_box(meta, b"iloc").unwrap()];
+    assert_eq!(&iloc[8..14], &[0, 0, 0, 0, 0x44, 0]);
+    let mut entry = 16;
+    let data = loop {
+        let extents = u16_at(iloc, entry + 4);
+        if u16_at(iloc, entry) == id {
+            assert_eq!(extents, 1);
+            let offset = u32_at(iloc, entry + 6);
+            break avif[offset..offset + u32_at(iloc, entry + 10)].to_vec();
+        }
+        entry += 6 + extents * 8;
+    };
+
+    let iref = &meta[find_box(meta, b"iref").unwrap()];
+    let mut refs = &iref[12..];
+    let mut described = Vec::new();
+    while !refs.is_empty() {
+        let reference = &refs[..u32_at(refs, 0)];
+        if &reference[4..8] == b"cdsc" && u16_at(reference, 8) == id {
+            described.extend((0..u16_at(reference, 10)).map(|i| u16_at(reference, 12 + i * 2)));
+        }
+        refs = &refs[reference.len()..];
+    }
+
+    Some((data, described))
+}
+
+/// Encode an image with the given metadata, and check that the image data and the metadata
+/// survive.
+fn embeds_metadata(channels: usize, exif: bool, xmp: bool, icc_profile: bool) {
+    let format = match channels {
+        4 => ImageFormat::RGBA8,
+        _ => ImageFormat::RGB8,
+    };
+    let mut img = Image::new(pixels(channels), format, 16, 16);
+    let plain = wimg::avif::encode(&img, &OPTS).unwrap();
+    img.set_metadata(Some(Metadata {
+        exif: exif.then(|| EXIF.to_vec()),
+        xmp: xmp.then(|| XMP.to_string()),
+    }));
+    if icc_profile {
+        img.set_icc_profile(Some(ICC_PROFILE.to_vec()));
+    }
+    let avif = wimg::avif::encode(&img, &OPTS).unwrap();
+    let avif: &[u8] = avif.as_ref();
+
+    let expected = avif_parse::read_avif(&mut plain.as_ref()).unwrap();
+    let parsed = avif_parse::read_avif(&mut &avif[..]).unwrap();
+    assert_eq!(parsed.primary_item, expected.primary_item);
+    assert_eq!(parsed.alpha_item, expected.alpha_item);
+
+    let decoded = wimg::avif::decode(avif).unwrap();
+    assert_close(decoded.as_ref(), &pixels(channels));
+    assert_eq!(decoded.icc_profile().is_some(), icc_profile);
+
+    // the primary item is the first one written by ravif
+    let exif_item = item(avif, b"Exif");
+    assert_eq!(exif_item.is_some(), exif);
+    if let Some((data, described)) = exif_item {
+        assert_eq!(data, [&[0; 4], EXIF].concat());
+        assert_eq!(described, [1]);
+    }
+    let xmp_item = item(avif, b"mime");
+    assert_eq!(xmp_item.is_some(), xmp);
+    if let Some((data, described)) = xmp_item {
+        assert_eq!(data, XMP.as_bytes());
+        assert_eq!(described, [1]);
+    }
+}
+
+#[test]
+fn embeds_exif() {
+    embeds_metadata(3, true, false, false);
+    embeds_metadata(4, true, false, false);
+}
+
+#[test]
+fn embeds_xmp() {
+    embeds_metadata(3, false, true, false);
+    embeds_metadata(4, false, true, false);
+}
+
+#[test]
+fn embeds_metadata_and_icc_profile() {
+    embeds_metadata(3, true, true, true);
+    embeds_metadata(4, true, true, true);
+}
+
+#[test]
+fn ignores_truncated_icc_profile_box() {
+    let mut img = Image::new(pixels(3), ImageFormat::RGB8, 16, 16);
+    img.set_icc_profile(Some(ICC_PROFILE.to_vec()));
+    let mut avif = wimg::avif::encode(&img, &OPTS).unwrap().as_ref().to_vec();
+
+    // declare a `colr` box too small to hold any profile data, while its `prof` type and the
+    // profile still follow
+    let colr = avif.windows(8).position(|w| w == b"colrprof").unwrap() - 4;
+    avif[colr..colr + 4].copy_from_slice(&10u32.to_be_bytes());
+    if let Ok(decoded) = wimg::avif::decode(&avif) {
+        assert_eq!(decoded.icc_profile(), None);
+    }
+}
@@ -0,0 +1,29 @@
+#![cfg(feature = "color")]
+
+/// A gray 8x8 PNG with a `gAMA` chunk (but no `iCCP`, `sRGB` or `cHRM` chunk).
+fn gamma_png() -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, 8, 8);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_source_gamma(png::ScaledFloat::new(1.0 / 1.8));
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(&[100; 8 * 8 * 3]).unwrap();
+    writer.finish().unwrap();
+    out
+}
+
+#[test]
+fn png_gamma_is_only_used_for_conversion() {
+    let img = wimg::png::decode(&gamma_png()).unwrap();
+    assert_eq!(img.icc_profile(), None);
+    assert_eq!(img.as_ref(), &[100; 8 * 8 * 3][..]);
+
+    let opts = wimg::png::DecodeOptions {
+        to_srgb: true,
+        ..Default::default()
+    };
+    let img = wimg::png::decode_with_options(&gamma_png(), &opts).unwrap();
+    assert_eq!(img.icc_profile(), None);
+    // mid tones encoded with a gamma of 1.8 become brighter in sRGB
+    assert!(img.as_ref().iter().all(|v| *v > 100));
+}
@@ -0,0 +1,185 @@
+use wimg::crop::{crop, crop_around, crop_rect, FocalPoint, Gravity};
+use wimg::error::Error;
+use wimg::{Image, ImageFormat};
+
+/// A 4x3 RGB image in which each pixel holds its own index.
+fn fixture() -> Image {
+    let data = (0..12u8).flat_map(|i| [i; 3]).collect();
+    Image::new(data, ImageFormat::RGB8, 4, 3)
+}
+
+fn pixels(img: &Image) -> Vec<u8> {
+    img.as_ref().chunks_exact(3).map(|p| p[0]).collect()
+}
+
+#[test]
+fn crops_rectangle() {
+    let img = crop_rect(&fixture(), 1, 1, 3, 2).unwrap();
+    assert_eq!((img.width(), img.height()), (3, 2));
+    assert_eq!(pixels(&img), [5, 6, 7, 9, 10, 11]);
+}
+
+#[test]
+fn crops_whole_image() {
+    let img = crop_rect(&fixture(), 0, 0, 4, 3).unwrap();
+    assert_eq!(pixels(&img), (0..12).collect::<Vec<_>>());
+}
+
+#[test]
+fn crops_centre() {
+    let img = crop(&fixture(), 2, 1, Gravity::Center).unwrap();
+    assert_eq!(pixels(&img), [5, 6]);
+}
+
+#[test]
+fn crops_with_gravity() {
+    for (gravity, expected) in [
+        (Gravity::North, [1, 2]),
+        (Gravity::NorthEast, [2, 3]),
+        (Gravity::East, [6, 7]),
+        (Gravity::SouthEast, [10, 11]),
+        (Gravity::South, [9, 10]),
+        (Gravity::SouthWest, [8, 9]),
+        (Gravity::West, [4, 5]),
+        (Gravity::NorthWest, [0, 1]),
+    ] {
+        let img = crop(&fixture(), 2, 1, gravity).unwrap();
+        assert_eq!(pixels(&img), expected, "{gravity:?}");
+    }
+}
+
+#[test]
+fn crops_around_focal_point() {
+    for ((x, y), expected) in [
+        ((0.5, 0.5), [5, 6]),
+        ((0.0, 0.0), [0, 1]),
+        ((1.0, 1.0), [10, 11]),
+        ((0.3, 0.9), [8, 9]),
+        // clamped to the image bounds
+        ((-2.0, 5.0), [8, 9]),
+    ] {
+        let img = crop_around(&fixture(), 2, 1, FocalPoint { x, y }).unwrap();
+        assert_eq!(pixels(&img), expected, "{x}/{y}");
+    }
+}
+
+/// A grey RGB image of `width`x`height`, with `paint` applied to each pixel.
+fn canvas(width: u32, height: u32, paint: impl Fn(u32, u32) -> Option<[u8; 3]>) -> Image {
+    let mut data = Vec::with_capacity((width * height * 3) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            data.extend_from_slice(&paint(x, y).unwrap_or([128; 3]));
+        }
+    }
+    Image::new(data, ImageFormat::RGB8, width, height)
+}
+
+#[test]
+fn crops_detail_with_attention() {
+    // a checkerboard in the right quarter
+    let img = canvas(40, 10, |x, y| {
+        (x >= 30).then(|| if (x + y) % 2 == 0 { [0; 3] } else { [255; 3] })
+    });
+    let cropped = crop(&img, 10, 10, Gravity::Attention).unwrap();
+    assert_eq!(
+        cropped.as_ref(),
+        crop_rect(&img, 30, 0, 10, 10).unwrap().as_ref()
+    );
+}
+
+#[test]
+fn crops_skin_with_attention() {
+    // a skin-colored patch at the top
+    let img = canvas(20, 60, |x, y| {
+        (y < 20 && (5..15).contains(&x)).then_some([224, 172, 140])
+    });
+    let cropped = crop(&img, 20, 20, Gravity::Attention).unwrap();
+    assert_eq!(
+        cropped.as_ref(),
+        crop_rect(&img, 0, 0, 20, 20).unwrap().as_ref()
+    );
+}
+
+#[test]
+fn crops_attention_on_proxy() {
+    // larger than the proxy, with a saturated patch at the bottom left
+    let img = canvas(1000, 2000, |x, y| {
+        (x < 500 && y >= 1500).then_some([200, 30, 30])
+    });
+    let cropped = crop(&img, 500, 500, Gravity::Attention).unwrap();
+    assert_eq!(
+        cropped.as_ref(),
+        crop_rect(&img, 0, 1500, 500, 500).unwrap().as_ref()
+    );
+}
+
+#[test]
+fn crops_plain_centre_with_attention() {
+    // fully transparent, so nothing is interesting; the red channel holds the column
+    let data = (0..30u8)
+        .cycle()
+        .take(30 * 10)
+        .flat_map(|x| [x, 0, 0, 0])
+        .collect();
+    let img = Image::new(data, ImageFormat::RGBA8, 30, 10);
+    let cropped = crop(&img, 10, 10, Gravity::Attention).unwrap();
+    assert_eq!(cropped.as_ref()[0], 10);
+}
+
+#[test]
+fn rejects_out_of_bounds() {
+    for (x, y, width, height) in [
+        (1, 0, 4, 3),
+        (0, 1, 4, 3),
+        (4, 0, 1, 1),
+        (0, 0, 0, 1),
+        (0, 0, 1, 0),
+        (u32::MAX, 0, 2, 1),
+    ] {
+        let err = crop_rect(&fixture(), x, y, width, height).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                Error::CropOutOfBounds {
+                    image_width: 4,
+                    image_height: 3,
+                    ..
+                }
+            ),
+            "{x}/{y} {width}x{height}: {err}"
+        );
+    }
+}
+
+#[test]
+fn rejects_out_of_bounds_with_gravity() {
+    let empty = Image::new(vec![], ImageFormat::RGB8, 0, 10);
+    for gravity in [Gravity::Center, Gravity::NorthEast, Gravity::Attention] {
+        for (img, width, height) in [(&empty, 0, 5), (&fixture(), 5, 2), (&fixture(), 2, 0)] {
+            assert!(
+                matches!(
+                    crop(img, width, height, gravity),
+                    Err(Error::CropOutOfBounds { .. })
+                ),
+                "{gravity:?} {width}x{height}"
+            );
+        }
+    }
+}
+
+#[test]
+fn rejects_encoded_images() {
+    let img = Image::new(vec![0; 4], ImageFormat::PNG, 1, 1);
+    assert!(matches!(
+        crop_rect(&img, 0, 0, 1, 1),
+        Err(Error::Process { .. })
+    ));
+}
+
+#[test]
+fn converts_gravity_from_u8() {
+    for value in 0..=9u8 {
+        assert_eq!(Gravity::try_from(value).unwrap() as u8, value);
+    }
+    assert!(Gravity::try_from(10).is_err());
+}
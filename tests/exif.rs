@@ -0,0 +1,422 @@
+use wimg::exif::{self, ByteOrder, Tiff};
+use wimg::{Image, ImageFormat};
+
+/// An IFD entry as `(tag, field type, count, inline value or value offset)`.
+type RawEntry = (u16, u16, u32, [u8; 4]);
+
+/// Build a little-endian TIFF structure with a single IFD0 holding `entries`, followed by `extra`
+/// data (which starts at offset `tiff_len(entries.len())`).
+fn tiff(ifd0_offset: u32, entry_count: u16, entries: &[RawEntry], extra: &[u8]) -> Vec<u8> {
+    let mut data = b"II".to_vec();
+    data.extend_from_slice(&0x002au16.to_le_bytes());
+    data.extend_from_slice(&ifd0_offset.to_le_bytes());
+    data.extend_from_slice(&entry_count.to_le_bytes());
+    for (tag, field_type, count, value) in entries {
+        data.extend_from_slice(&tag.to_le_bytes());
+        data.extend_from_slice(&field_type.to_le_bytes());
+        data.extend_from_slice(&count.to_le_bytes());
+        data.extend_from_slice(value);
+    }
+    data.extend_from_slice(&0u32.to_le_bytes()); // no next IFD
+    data.extend_from_slice(extra);
+    data
+}
+
+/// Offset of the data following an IFD0 with `entries` entries built by [`tiff`].
+fn tiff_len(entries: usize) -> u32 {
+    8 + 2 + entries as u32 * 12 + 4
+}
+
+fn orientation_entry(orientation: u16) -> RawEntry {
+    let [lo, hi] = orientation.to_le_bytes();
+    (exif::ORIENTATION, 3, 1, [lo, hi, 0, 0])
+}
+
+/// Wrap `segments` (as `(marker, payload)`) into a JPEG, followed by SOS and some scan data.
+fn jpeg(segments: &[(u8, &[u8])]) -> Vec<u8> {
+    let mut data = vec![0xFF, 0xD8];
+    for (marker, payload) in segments {
+        data.extend_from_slice(&[0xFF, *marker]);
+        data.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+        data.extend_from_slice(payload);
+    }
+    data.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x02, 0x12, 0x34, 0xFF, 0xD9]);
+    data
+}
+
+fn exif_payload(tiff: &[u8]) -> Vec<u8> {
+    let mut payload = b"Exif\0\0".to_vec();
+    payload.extend_from_slice(tiff);
+    payload
+}
+
+fn exif_jpeg(orientation: u16) -> Vec<u8> {
+    let tiff = tiff(8, 1, &[orientation_entry(orientation)], &[]);
+    jpeg(&[(0xE0, b"JFIF\0\x01\x01"), (0xE1, &exif_payload(&tiff))])
+}
+
+/// Walk everything reachable from `tiff`, to make sure none of it panics.
+fn walk(data: &[u8]) {
+    let Some(tiff) = Tiff::parse(data) else {
+        return;
+    };
+    let mut ifd = tiff.ifd0();
+    // IFD chains can be cyclic
+    for _ in 0..8 {
+        let Some(current) = ifd else {
+            break;
+        };
+        for entry in current.entries() {
+            let _ = (entry.u16(), entry.u32(), entry.value().len());
+            if let Some(offset) = entry.u32() {
+                if let Some(sub) = tiff.ifd_at(offset) {
+                    sub.entries().for_each(drop);
+                }
+            }
+        }
+        ifd = current.next_ifd();
+    }
+}
+
+#[test]
+fn reads_orientation() {
+    for orientation in 1..=8 {
+        assert_eq!(
+            exif::orientation(&exif_jpeg(orientation)),
+            Some(orientation)
+        );
+    }
+}
+
+#[test]
+fn reads_big_endian() {
+    let mut tiff = b"MM\x00\x2a\x00\x00\x00\x08\x00\x01".to_vec();
+    tiff.extend_from_slice(&[
+        0x01, 0x12, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01, 0x00, 0x06, 0, 0,
+    ]);
+    tiff.extend_from_slice(&[0, 0, 0, 0]);
+    let data = jpeg(&[(0xE1, &exif_payload(&tiff))]);
+
+    assert_eq!(
+        Tiff::parse(exif::jpeg_exif(&data).unwrap())
+            .unwrap()
+            .byte_order(),
+        ByteOrder::BigEndian
+    );
+    assert_eq!(exif::orientation(&data), Some(6));
+}
+
+#[test]
+fn truncated_jpeg() {
+    let data = exif_jpeg(6);
+    let app1_end = data.len() - 8;
+    for len in 0..data.len() {
+        let orientation = exif::orientation(&data[..len]);
+        if len < app1_end {
+            assert_eq!(orientation, None, "truncated to {len} bytes");
+        } else {
+            assert_eq!(orientation, Some(6), "truncated to {len} bytes");
+        }
+    }
+}
+
+#[test]
+fn truncated_tiff() {
+    // keep the segment length consistent, so only the TIFF structure itself is truncated
+    let tiff = tiff(8, 1, &[orientation_entry(6)], &[]);
+    for len in 0..tiff.len() {
+        walk(&tiff[..len]);
+        let data = jpeg(&[(0xE1, &exif_payload(&tiff[..len]))]);
+        let expected = if len >= 8 + 2 + 12 { Some(6) } else { None };
+        assert_eq!(
+            exif::orientation(&data),
+            expected,
+            "truncated to {len} bytes"
+        );
+    }
+}
+
+#[test]
+fn malformed_segment_lengths() {
+    for len in [0u16, 1, 0xFFFF] {
+        let mut data = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        data.extend_from_slice(&len.to_be_bytes());
+        data.extend_from_slice(b"Exif\0\0II");
+        assert_eq!(exif::segments(&data).count(), 0, "length {len}");
+        assert_eq!(exif::orientation(&data), None, "length {len}");
+    }
+}
+
+#[test]
+fn missing_soi() {
+    let data = exif_jpeg(6);
+    assert_eq!(exif::segments(&data[2..]).count(), 0);
+    assert_eq!(exif::orientation(&data[1..]), None);
+    assert_eq!(exif::orientation(&[]), None);
+}
+
+#[test]
+fn fill_bytes_and_standalone_markers() {
+    let data = exif_jpeg(6);
+    // SOI, fill bytes, a RST0 marker, more fill bytes, and then the rest of the segments
+    let mut padded = vec![0xFF, 0xD8, 0xFF, 0xFF, 0xFF, 0xD0, 0xFF, 0xFF];
+    padded.extend_from_slice(&data[2..]);
+    assert_eq!(exif::orientation(&padded), Some(6));
+
+    // trailing fill bytes without any marker
+    assert_eq!(exif::segments(&[0xFF, 0xD8, 0xFF, 0xFF, 0xFF]).count(), 0);
+}
+
+#[test]
+fn garbage_between_segments() {
+    let mut data = vec![0xFF, 0xD8, 0x00, 0x00];
+    data.extend_from_slice(&exif_jpeg(6)[2..]);
+    assert_eq!(exif::orientation(&data), None);
+}
+
+#[test]
+fn stops_at_start_of_scan() {
+    let tiff = tiff(8, 1, &[orientation_entry(6)], &[]);
+    let mut data = jpeg(&[(0xE0, b"JFIF\0")]);
+    // an APP1 segment after SOS is image data and must not be considered
+    let app1 = jpeg(&[(0xE1, &exif_payload(&tiff))]);
+    data.truncate(data.len() - 2);
+    data.extend_from_slice(&app1[2..]);
+
+    assert_eq!(exif::segments(&data).count(), 1);
+    assert_eq!(exif::orientation(&data), None);
+}
+
+#[test]
+fn skips_non_exif_app1() {
+    let tiff = tiff(8, 1, &[orientation_entry(8)], &[]);
+    let data = jpeg(&[
+        (0xE1, b"http://ns.adobe.com/xap/1.0/\0<x:xmpmeta/>"),
+        (0xE1, &exif_payload(&tiff)),
+    ]);
+    assert_eq!(exif::orientation(&data), Some(8));
+}
+
+#[test]
+fn invalid_tiff_header() {
+    assert!(Tiff::parse(b"XX\x2a\x00\x08\x00\x00\x00").is_none());
+    assert!(Tiff::parse(b"II\x2b\x00\x08\x00\x00\x00").is_none());
+    assert!(Tiff::parse(b"II\x2a\x00\x08").is_none());
+    assert!(Tiff::parse(b"II").is_none());
+}
+
+#[test]
+fn ifd_offset_out_of_bounds() {
+    for offset in [100, 0xFFFF_FFF0, u32::MAX] {
+        let tiff = tiff(offset, 1, &[orientation_entry(6)], &[]);
+        walk(&tiff);
+        assert!(
+            Tiff::parse(&tiff).unwrap().ifd0().is_none(),
+            "offset {offset}"
+        );
+        let data = jpeg(&[(0xE1, &exif_payload(&tiff))]);
+        assert_eq!(exif::orientation(&data), None, "offset {offset}");
+    }
+}
+
+#[test]
+fn entry_count_exceeds_data() {
+    let tiff = tiff(8, 0xFFFF, &[orientation_entry(3)], &[]);
+    walk(&tiff);
+    let ifd0 = Tiff::parse(&tiff).unwrap().ifd0().unwrap();
+    // only the orientation entry fits, the remaining bytes are too short for another entry
+    assert_eq!(ifd0.entries().count(), 1);
+    assert_eq!(ifd0.get(exif::ORIENTATION).and_then(|e| e.u16()), Some(3));
+    assert!(ifd0.next_ifd().is_none());
+}
+
+#[test]
+fn value_offsets() {
+    let offset = tiff_len(3);
+    let tiff = tiff(
+        8,
+        3,
+        &[
+            // ASCII value stored after the IFD
+            (0x010F, 2, 6, offset.to_le_bytes()),
+            // RATIONAL value out of bounds
+            (0x011A, 5, 1, 1000u32.to_le_bytes()),
+            orientation_entry(6),
+        ],
+        b"Canon\0",
+    );
+    walk(&tiff);
+
+    let ifd0 = Tiff::parse(&tiff).unwrap().ifd0().unwrap();
+    assert_eq!(ifd0.get(0x010F).unwrap().value(), b"Canon\0");
+    assert!(ifd0.get(0x011A).is_none());
+    assert_eq!(ifd0.get(exif::ORIENTATION).and_then(|e| e.u16()), Some(6));
+}
+
+#[test]
+fn value_size_overflow() {
+    let tiff = tiff(
+        8,
+        3,
+        &[
+            (0x010F, 2, u32::MAX, u32::MAX.to_le_bytes()),
+            (0x011A, 12, u32::MAX, 8u32.to_le_bytes()),
+            (0x8769, 4, 0x4000_0000, 0u32.to_le_bytes()),
+        ],
+        &[],
+    );
+    walk(&tiff);
+    assert_eq!(
+        Tiff::parse(&tiff)
+            .unwrap()
+            .ifd0()
+            .unwrap()
+            .entries()
+            .count(),
+        0
+    );
+}
+
+#[test]
+fn unexpected_types() {
+    let tiff = tiff(
+        8,
+        3,
+        &[
+            // unknown field type
+            (0x0100, 0x00FF, 1, [1, 0, 0, 0]),
+            // orientation as LONG
+            (exif::ORIENTATION, 4, 1, [6, 0, 0, 0]),
+            // ASCII with a count of 0
+            (0x010F, 2, 0, [0, 0, 0, 0]),
+        ],
+        &[],
+    );
+    let ifd0 = Tiff::parse(&tiff).unwrap().ifd0().unwrap();
+    assert!(ifd0.get(0x0100).is_none());
+    let orientation = ifd0.get(exif::ORIENTATION).unwrap();
+    assert_eq!(orientation.u16(), None);
+    assert_eq!(orientation.u32(), Some(6));
+    assert_eq!(ifd0.get(0x010F).unwrap().value(), b"");
+
+    let data = jpeg(&[(0xE1, &exif_payload(&tiff))]);
+    assert_eq!(exif::orientation(&data), None);
+}
+
+#[test]
+fn cyclic_ifds() {
+    let mut tiff = tiff(8, 1, &[orientation_entry(6)], &[]);
+    // point the next IFD back to IFD0
+    let next = tiff_len(1) as usize - 4;
+    tiff[next..next + 4].copy_from_slice(&8u32.to_le_bytes());
+    walk(&tiff);
+
+    let ifd0 = Tiff::parse(&tiff).unwrap().ifd0().unwrap();
+    let next = ifd0.next_ifd().unwrap();
+    assert_eq!(next.get(exif::ORIENTATION).and_then(|e| e.u16()), Some(6));
+}
+
+#[test]
+fn corrupted_bytes() {
+    let tiff = tiff(
+        8,
+        2,
+        &[
+            (0x010F, 2, 6, tiff_len(2).to_le_bytes()),
+            orientation_entry(6),
+        ],
+        b"Canon\0",
+    );
+    let data = jpeg(&[(0xE1, &exif_payload(&tiff))]);
+
+    for i in 0..data.len() {
+        for byte in [0x00, 0x01, 0x7F, 0xFF] {
+            let mut data = data.clone();
+            data[i] = byte;
+            let _ = exif::orientation(&data);
+            exif::segments(&data).for_each(drop);
+            if let Some(tiff) = exif::jpeg_exif(&data) {
+                walk(tiff);
+            }
+        }
+    }
+}
+
+#[test]
+fn decodes_jpeg_with_malformed_exif() {
+    let img = Image::new(vec![128; 8 * 8 * 3], ImageFormat::RGB8, 8, 8);
+    let encoded = wimg::jpeg::encode(&img, &Default::default()).unwrap();
+    let encoded = encoded.as_ref();
+
+    let malformed = [
+        tiff(u32::MAX, 1, &[orientation_entry(6)], &[]),
+        tiff(8, 0xFFFF, &[], &[]),
+        b"II\x2a\x00".to_vec(),
+    ];
+    for tiff in malformed {
+        let mut data = encoded[..2].to_vec();
+        let payload = exif_payload(&tiff);
+        data.extend_from_slice(&[0xFF, 0xE1]);
+        data.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+        data.extend_from_slice(&payload);
+        data.extend_from_slice(&encoded[2..]);
+
+        let decoded = wimg::jpeg::decode(&data).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (8, 8));
+    }
+}
+
+#[test]
+fn resets_orientation() {
+    let mut tiff = tiff(8, 1, &[orientation_entry(6)], &[]);
+    exif::reset_orientation(&mut tiff);
+    let ifd0 = Tiff::parse(&tiff).unwrap().ifd0().unwrap();
+    assert_eq!(ifd0.get(exif::ORIENTATION).and_then(|e| e.u16()), Some(1));
+
+    // malformed data is left alone
+    let mut malformed = b"II\x2a\x00".to_vec();
+    exif::reset_orientation(&mut malformed);
+    assert_eq!(malformed, b"II\x2a\x00");
+}
+
+#[test]
+fn retains_allowlisted_tags() {
+    let values = tiff_len(4);
+    let gps = values + 20;
+    // two ASCII values, padded to a word boundary
+    let mut extra = b"Jane Doe\0(c) Jane\0\0\0".to_vec();
+    // GPS IFD with a single GPSVersionID entry
+    extra.extend_from_slice(&1u16.to_le_bytes());
+    extra.extend_from_slice(&[0, 0, 1, 0, 4, 0, 0, 0, 2, 2, 0, 0]);
+    extra.extend_from_slice(&0u32.to_le_bytes());
+    let tiff = tiff(
+        8,
+        4,
+        &[
+            orientation_entry(1),
+            (exif::ARTIST, 2, 9, values.to_le_bytes()),
+            (exif::COPYRIGHT, 2, 9, (values + 9).to_le_bytes()),
+            (exif::GPS_IFD, 4, 1, gps.to_le_bytes()),
+        ],
+        &extra,
+    );
+
+    let retained = exif::retain(&tiff, &[exif::ARTIST, exif::COPYRIGHT]).unwrap();
+    walk(&retained);
+    let parsed = Tiff::parse(&retained).unwrap();
+    assert_eq!(parsed.byte_order(), ByteOrder::LittleEndian);
+    let ifd0 = parsed.ifd0().unwrap();
+    assert_eq!(ifd0.entries().count(), 2);
+    assert_eq!(ifd0.get(exif::ARTIST).unwrap().value(), b"Jane Doe\0");
+    assert_eq!(ifd0.get(exif::COPYRIGHT).unwrap().value(), b"(c) Jane\0");
+    assert!(ifd0.get(exif::GPS_IFD).is_none());
+
+    let retained = exif::retain(&tiff, &[exif::GPS_IFD]).unwrap();
+    let parsed = Tiff::parse(&retained).unwrap();
+    let gps = parsed.ifd0().unwrap().get(exif::GPS_IFD).unwrap();
+    let gps = parsed.ifd_at(gps.u32().unwrap()).unwrap();
+    assert_eq!(gps.get(0x0000).unwrap().value(), [2, 2, 0, 0]);
+
+    assert!(exif::retain(&tiff, &[]).is_none());
+    assert!(exif::retain(&tiff, &[exif::MAKE]).is_none());
+}
@@ -0,0 +1,25 @@
+/// A 4x4 GIF with a frame for each delay (in units of 10ms).
+fn fixture(delays: &[u16]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut encoder = gif::Encoder::new(&mut out, 4, 4, &[0, 0, 0, 255, 255, 255]).unwrap();
+    for (i, delay) in delays.iter().enumerate() {
+        let frame = gif::Frame {
+            width: 4,
+            height: 4,
+            delay: *delay,
+            buffer: vec![(i % 2) as u8; 16].into(),
+            ..Default::default()
+        };
+        encoder.write_frame(&frame).unwrap();
+    }
+    drop(encoder);
+    out
+}
+
+#[test]
+fn decodes_frame_delays() {
+    let animation = wimg::gif::decode_animation(&fixture(&[0, 1, 2, 5, 50])).unwrap();
+    let delays: Vec<_> = animation.frames().iter().map(|f| f.delay()).collect();
+    // delays of 10ms or less are played with 100ms, like browsers do
+    assert_eq!(delays, [100, 100, 20, 50, 500]);
+}
@@ -0,0 +1,86 @@
+use wimg::jxl::EncodeOptions;
+use wimg::{Image, ImageFormat};
+
+const LOSSLESS: EncodeOptions = EncodeOptions {
+    quality: 100,
+    effort: 3,
+    lossless: true,
+    threads: 0,
+};
+
+/// A 32x32 image with a horizontal red and a vertical blue gradient.
+fn pixels(channels: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(32 * 32 * channels);
+    for y in 0..32u8 {
+        for x in 0..32u8 {
+            data.extend_from_slice(&[x * 8, 128, y * 8, 255 - x * 4][..channels]);
+        }
+    }
+    data
+}
+
+#[test]
+fn encodes_lossless() {
+    for (format, channels) in [(ImageFormat::RGB8, 3), (ImageFormat::RGBA8, 4)] {
+        let img = Image::new(pixels(channels), format, 32, 32);
+        let jxl = wimg::jxl::encode(&img, &LOSSLESS).unwrap();
+
+        let info = wimg::jxl::probe(jxl.as_ref()).unwrap();
+        assert_eq!((info.width, info.height), (32, 32));
+        assert_eq!(info.has_alpha, channels == 4);
+
+        let decoded = wimg::jxl::decode(jxl.as_ref()).unwrap();
+        assert_eq!(decoded.as_ref(), &pixels(channels)[..]);
+    }
+}
+
+#[test]
+fn encodes_lossless_16bit() {
+    let data = pixels(3)
+        .into_iter()
+        .flat_map(|v| (u16::from(v) * 257).to_ne_bytes())
+        .collect();
+    let img = Image::new(data, ImageFormat::RGB16, 32, 32);
+    let jxl = wimg::jxl::encode(&img, &LOSSLESS).unwrap();
+
+    let info = wimg::jxl::probe(jxl.as_ref()).unwrap();
+    assert_eq!(info.bit_depth, 16);
+}
+
+#[cfg(not(feature = "libjxl"))]
+#[test]
+fn rejects_lossy_without_libjxl() {
+    let img = Image::new(pixels(3), ImageFormat::RGB8, 32, 32);
+    let err = wimg::jxl::encode(&img, &EncodeOptions::default()).unwrap_err();
+    assert!(matches!(
+        err,
+        wimg::error::Error::Jxl(wimg::jxl::JxlError::LossyUnsupported)
+    ));
+}
+
+#[cfg(feature = "libjxl")]
+#[test]
+fn encodes_lossy() {
+    for (format, channels) in [(ImageFormat::RGB8, 3), (ImageFormat::RGBA8, 4)] {
+        let img = Image::new(pixels(channels), format, 32, 32);
+        let lossless = wimg::jxl::encode(&img, &LOSSLESS).unwrap();
+        let opts = EncodeOptions {
+            quality: 90,
+            lossless: false,
+            threads: 2,
+            ..LOSSLESS
+        };
+        let jxl = wimg::jxl::encode(&img, &opts).unwrap();
+        assert!(jxl.as_ref().len() < lossless.as_ref().len());
+
+        let decoded = wimg::jxl::decode(jxl.as_ref()).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (32, 32));
+        // individual samples at the saturated edges can be off by more, so compare the mean error
+        let expected = pixels(channels);
+        assert_eq!(decoded.as_ref().len(), expected.len());
+        let error: u32 = (decoded.as_ref().iter().zip(&expected))
+            .map(|(a, e)| u32::from(a.abs_diff(*e)))
+            .sum();
+        assert!(error < 4 * expected.len() as u32, "total error {error}");
+    }
+}
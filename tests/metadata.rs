@@ -0,0 +1,102 @@
+use wimg::exif::{self, Tiff};
+use wimg::metadata::Allowlist;
+use wimg::{Image, ImageFormat};
+
+const XMP: &str = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"><rdf:Description xmlns:exif="http://ns.adobe.com/exif/1.0/" exif:GPSLatitude="52,31.2N"/></rdf:RDF></x:xmpmeta>"#;
+
+/// A little-endian TIFF structure with an artist and a GPS IFD holding a single GPSVersionID.
+fn tiff() -> Vec<u8> {
+    let mut data = b"II\x2a\x00".to_vec();
+    data.extend_from_slice(&8u32.to_le_bytes());
+    data.extend_from_slice(&2u16.to_le_bytes());
+    // artist, stored inline
+    data.extend_from_slice(&exif::ARTIST.to_le_bytes());
+    data.extend_from_slice(&[2, 0, 4, 0, 0, 0]);
+    data.extend_from_slice(b"Doe\0");
+    // GPS IFD, following IFD0
+    data.extend_from_slice(&exif::GPS_IFD.to_le_bytes());
+    data.extend_from_slice(&[4, 0, 1, 0, 0, 0]);
+    data.extend_from_slice(&38u32.to_le_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes());
+    data.extend_from_slice(&1u16.to_le_bytes());
+    data.extend_from_slice(&[0, 0, 1, 0, 4, 0, 0, 0, 2, 2, 0, 0]);
+    data.extend_from_slice(&0u32.to_le_bytes());
+    data
+}
+
+/// A JPEG with an Exif segment (including GPS data) and an XMP segment (including a location).
+fn fixture() -> Vec<u8> {
+    let img = Image::new(vec![128; 8 * 8 * 3], ImageFormat::RGB8, 8, 8);
+    let jpeg = wimg::jpeg::encode(&img, &Default::default()).unwrap();
+    let jpeg = jpeg.as_ref();
+
+    let mut exif = b"Exif\0\0".to_vec();
+    exif.extend_from_slice(&tiff());
+    let mut xmp = b"http://ns.adobe.com/xap/1.0/\0".to_vec();
+    xmp.extend_from_slice(XMP.as_bytes());
+
+    // insert the APP1 segments right after SOI
+    let mut out = jpeg[..2].to_vec();
+    for payload in [exif, xmp] {
+        out.extend_from_slice(&[0xFF, 0xE1]);
+        out.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+        out.extend_from_slice(&payload);
+    }
+    out.extend_from_slice(&jpeg[2..]);
+    out
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+#[test]
+fn strips_metadata_by_default() {
+    let img = wimg::jpeg::decode(&fixture()).unwrap();
+    assert!(img.metadata().is_none());
+
+    let img = wimg::decode(&fixture()).unwrap();
+    assert!(img.metadata().is_none());
+
+    let webp = wimg::webp::encode(&img, &Default::default()).unwrap();
+    let avif = wimg::avif::encode(&img, &Default::default()).unwrap();
+    for encoded in [webp.as_ref(), avif.as_ref()] {
+        assert!(!contains(encoded, &tiff()[8..]));
+        assert!(!contains(encoded, b"GPSLatitude"));
+    }
+}
+
+#[test]
+fn keeps_allowlisted_metadata() {
+    let opts = wimg::jpeg::DecodeOptions {
+        keep_metadata: Allowlist::copyright(),
+        ..Default::default()
+    };
+    let img = wimg::jpeg::decode_with_options(&fixture(), &opts).unwrap();
+    let metadata = img.metadata().unwrap();
+    assert!(metadata.xmp.is_none());
+    let exif = metadata.exif.as_deref().unwrap();
+    let ifd0 = Tiff::parse(exif).unwrap().ifd0().unwrap();
+    assert_eq!(ifd0.get(exif::ARTIST).unwrap().value(), b"Doe\0");
+    assert!(ifd0.get(exif::GPS_IFD).is_none());
+
+    let webp = wimg::webp::encode(&img, &Default::default()).unwrap();
+    assert!(contains(webp.as_ref(), b"EXIF"));
+    assert!(!contains(webp.as_ref(), b"GPSLatitude"));
+
+    let opts = wimg::jpeg::DecodeOptions {
+        keep_metadata: Allowlist {
+            exif_tags: vec![exif::GPS_IFD],
+            xmp: true,
+        },
+        ..Default::default()
+    };
+    let img = wimg::jpeg::decode_with_options(&fixture(), &opts).unwrap();
+    let metadata = img.metadata().unwrap();
+    assert_eq!(metadata.xmp.as_deref(), Some(XMP));
+    let exif = metadata.exif.as_deref().unwrap();
+    let tiff = Tiff::parse(exif).unwrap();
+    assert!(tiff.ifd0().unwrap().get(exif::GPS_IFD).is_some());
+}
@@ -0,0 +1,96 @@
+//! The fixtures all show the same 4x2 grid of colored blocks once their Exif orientation is
+//! applied, with the pixels stored accordingly (like the photos of a rotated camera). They were
+//! encoded by the `jpeg-encoder` crate (4:4:4, quality 95), independently of wimg's own encoder.
+
+const BLOCK: u32 = 16;
+const COLUMNS: u32 = 4;
+const ROWS: u32 = 2;
+
+/// The color of each block of the upright image.
+const COLORS: [[u8; 3]; 8] = [
+    [255, 0, 0],
+    [0, 255, 0],
+    [0, 0, 255],
+    [255, 255, 0],
+    [0, 255, 255],
+    [255, 0, 255],
+    [255, 255, 255],
+    [0, 0, 0],
+];
+
+fn check(name: &str, jpeg: &[u8]) {
+    let img = wimg::jpeg::decode(jpeg).unwrap();
+    let (width, height) = (BLOCK * COLUMNS, BLOCK * ROWS);
+    assert_eq!((img.width(), img.height()), (width, height), "{name}");
+
+    // compare the center pixel of each block
+    let data = img.as_ref();
+    for by in 0..ROWS {
+        for bx in 0..COLUMNS {
+            let (x, y) = (bx * BLOCK + BLOCK / 2, by * BLOCK + BLOCK / 2);
+            let expected = COLORS[(by * COLUMNS + bx) as usize];
+
+            let offset = ((y * width + x) * 3) as usize;
+            let actual = &data[offset..offset + 3];
+            for (a, e) in actual.iter().zip(expected) {
+                assert!(
+                    a.abs_diff(e) <= 24,
+                    "{name}: expected {expected:?} at ({x}, {y}), got {actual:?}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn without_exif() {
+    check("none", include_bytes!("fixtures/orientation/none.jpg"));
+}
+
+#[test]
+fn orientation_1() {
+    check("1", include_bytes!("fixtures/orientation/1.jpg"));
+}
+
+#[test]
+fn orientation_2_mirrored() {
+    check("2", include_bytes!("fixtures/orientation/2.jpg"));
+}
+
+#[test]
+fn orientation_3_rotated_180() {
+    check("3", include_bytes!("fixtures/orientation/3.jpg"));
+}
+
+#[test]
+fn orientation_4_flipped() {
+    check("4", include_bytes!("fixtures/orientation/4.jpg"));
+}
+
+#[test]
+fn orientation_5_transposed() {
+    check("5", include_bytes!("fixtures/orientation/5.jpg"));
+}
+
+#[test]
+fn orientation_6_rotated_90() {
+    check("6", include_bytes!("fixtures/orientation/6.jpg"));
+}
+
+#[test]
+fn orientation_7_transversed() {
+    check("7", include_bytes!("fixtures/orientation/7.jpg"));
+}
+
+#[test]
+fn orientation_8_rotated_270() {
+    check("8", include_bytes!("fixtures/orientation/8.jpg"));
+}
+
+#[test]
+fn orientation_big_endian() {
+    check(
+        "7_big_endian",
+        include_bytes!("fixtures/orientation/7_big_endian.jpg"),
+    );
+}
@@ -0,0 +1,99 @@
+use wimg::crop::{FocalPoint, Gravity};
+use wimg::resize::{resize_with_options, Filter, ResizeOptions};
+use wimg::{Image, ImageFormat};
+
+const RED: [u8; 3] = [255, 0, 0];
+const BLUE: [u8; 3] = [0, 0, 255];
+
+/// An 8x2 image, red on the left and blue on the right half.
+fn fixture() -> Image {
+    let data = (0..16)
+        .flat_map(|i| if i % 8 < 4 { RED } else { BLUE })
+        .collect();
+    Image::new(data, ImageFormat::RGB8, 8, 2)
+}
+
+fn resize(opts: ResizeOptions) -> Vec<u8> {
+    let img = resize_with_options(&fixture(), 1, 1, &opts).unwrap();
+    assert_eq!((img.width(), img.height()), (1, 1));
+    img.as_ref().to_vec()
+}
+
+#[test]
+fn crops_with_gravity() {
+    let west = resize(ResizeOptions {
+        gravity: Gravity::West,
+        ..Default::default()
+    });
+    assert_eq!(west, RED);
+
+    let east = resize(ResizeOptions {
+        gravity: Gravity::SouthEast,
+        ..Default::default()
+    });
+    assert_eq!(east, BLUE);
+}
+
+#[test]
+fn crops_around_focal_point() {
+    let img = resize(ResizeOptions {
+        gravity: Gravity::West,
+        focal_point: Some(FocalPoint { x: 0.9, y: 0.5 }),
+        ..Default::default()
+    });
+    assert_eq!(img, BLUE);
+}
+
+#[test]
+fn maintains_aspect() {
+    let img = resize_with_options(
+        &fixture(),
+        4,
+        4,
+        &ResizeOptions {
+            maintain_aspect: true,
+            gravity: Gravity::West,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!((img.width(), img.height()), (4, 1));
+}
+
+#[test]
+fn defaults_to_triangle_filter() {
+    assert_eq!(ResizeOptions::default().filter, Filter::Triangle);
+}
+
+#[test]
+fn resamples_with_filter() {
+    // a white column between two black ones
+    let data = [0, 255, 0].repeat(3).iter().flat_map(|v| [*v; 3]).collect();
+    let img = Image::new(data, ImageFormat::RGB8, 3, 3);
+    let resize = |filter| {
+        let opts = ResizeOptions {
+            filter,
+            ..Default::default()
+        };
+        resize_with_options(&img, 1, 1, &opts).unwrap().as_ref()[0]
+    };
+
+    assert_eq!(resize(Filter::Point), 255);
+    for filter in [
+        Filter::Triangle,
+        Filter::CatmullRom,
+        Filter::Mitchell,
+        Filter::Lanczos3,
+    ] {
+        assert!(resize(filter) < 255, "{filter:?}");
+    }
+}
+
+#[test]
+fn converts_filter_from_u8() {
+    for value in 1..=5u8 {
+        assert_eq!(Filter::try_from(value).unwrap() as u8, value);
+    }
+    assert!(Filter::try_from(0).is_err());
+    assert!(Filter::try_from(6).is_err());
+}
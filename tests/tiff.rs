@@ -0,0 +1,33 @@
+use std::io::Cursor;
+
+use tiff::encoder::{colortype, TiffEncoder};
+
+#[test]
+fn decodes_cmyk() {
+    let cmyk = [
+        [0, 0, 0, 0],
+        [255, 0, 0, 0],
+        [0, 255, 255, 0],
+        [0, 0, 0, 255],
+        [0, 0, 0, 51],
+    ];
+    let mut data = Cursor::new(Vec::new());
+    TiffEncoder::new(&mut data)
+        .unwrap()
+        .write_image::<colortype::CMYK8>(cmyk.len() as u32, 1, cmyk.as_flattened())
+        .unwrap();
+
+    let img = wimg::tiff::decode(data.get_ref()).unwrap();
+    assert_eq!((img.width(), img.height()), (cmyk.len() as u32, 1));
+    assert_eq!(
+        img.as_ref(),
+        [
+            [255, 255, 255],
+            [0, 255, 255],
+            [255, 0, 0],
+            [0, 0, 0],
+            [204, 204, 204],
+        ]
+        .as_flattened()
+    );
+}
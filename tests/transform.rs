@@ -0,0 +1,125 @@
+use wimg::metadata::Metadata;
+use wimg::transform::{transform, Transform};
+use wimg::{Image, ImageFormat};
+
+const WIDTH: u32 = 3;
+const HEIGHT: u32 = 2;
+
+/// An image in which each pixel holds its own index, repeated for every byte of the pixel.
+fn fixture(format: ImageFormat, pixel_size: usize) -> Image {
+    let data = (0..(WIDTH * HEIGHT) as u8)
+        .flat_map(|i| std::iter::repeat_n(i, pixel_size))
+        .collect();
+    Image::new(data, format, WIDTH, HEIGHT)
+}
+
+/// The source pixel shown at `(x, y)` after applying `transform`.
+fn source(transform: Transform, x: u32, y: u32) -> (u32, u32) {
+    match transform {
+        Transform::Rotate90 => (y, HEIGHT - 1 - x),
+        Transform::Rotate180 => (WIDTH - 1 - x, HEIGHT - 1 - y),
+        Transform::Rotate270 => (WIDTH - 1 - y, x),
+        Transform::FlipHorizontal => (WIDTH - 1 - x, y),
+        Transform::FlipVertical => (x, HEIGHT - 1 - y),
+        Transform::Transpose => (y, x),
+        Transform::Transverse => (WIDTH - 1 - y, HEIGHT - 1 - x),
+    }
+}
+
+fn check(format: ImageFormat, pixel_size: usize) {
+    let img = fixture(format, pixel_size);
+    for t in [
+        Transform::Rotate90,
+        Transform::Rotate180,
+        Transform::Rotate270,
+        Transform::FlipHorizontal,
+        Transform::FlipVertical,
+        Transform::Transpose,
+        Transform::Transverse,
+    ] {
+        let out = transform(&img, t).unwrap();
+        let (width, height) = if t.transposes() {
+            (HEIGHT, WIDTH)
+        } else {
+            (WIDTH, HEIGHT)
+        };
+        assert_eq!((out.width(), out.height()), (width, height), "{t:?}");
+
+        let data = out.as_ref();
+        for y in 0..height {
+            for x in 0..width {
+                let (sx, sy) = source(t, x, y);
+                let offset = (y * width + x) as usize * pixel_size;
+                let expected = vec![(sy * WIDTH + sx) as u8; pixel_size];
+                assert_eq!(
+                    &data[offset..offset + pixel_size],
+                    &expected[..],
+                    "{t:?} at ({x}, {y})"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn rgb8() {
+    check(ImageFormat::RGB8, 3);
+}
+
+#[test]
+fn rgba8() {
+    check(ImageFormat::RGBA8, 4);
+}
+
+#[test]
+fn rgb16() {
+    check(ImageFormat::RGB16, 6);
+}
+
+#[test]
+fn rgba16() {
+    check(ImageFormat::RGBA16, 8);
+}
+
+#[test]
+fn from_exif() {
+    for orientation in 2..=8 {
+        let t = Transform::from_exif(orientation).unwrap();
+        assert_eq!(
+            t.transposes(),
+            orientation >= 5,
+            "orientation {orientation}"
+        );
+    }
+    assert_eq!(Transform::from_exif(1), None);
+    assert_eq!(Transform::from_exif(9), None);
+}
+
+#[test]
+fn resets_orientation() {
+    let mut img = fixture(ImageFormat::RGB8, 3);
+    img.set_metadata(Some(Metadata {
+        exif: None,
+        xmp: Some(r#"<rdf:Description tiff:Orientation="6"/>"#.to_string()),
+    }));
+    let out = transform(&img, Transform::Rotate90).unwrap();
+    assert_eq!(
+        out.metadata().and_then(|m| m.xmp.as_deref()),
+        Some(r#"<rdf:Description tiff:Orientation="1"/>"#)
+    );
+}
+
+#[test]
+fn rejects_encoded_images() {
+    let img = Image::new(vec![0; 4], ImageFormat::PNG, 1, 1);
+    assert!(transform(&img, Transform::Rotate90).is_err());
+}
+
+#[test]
+fn converts_from_u8() {
+    for value in 1..=7u8 {
+        assert_eq!(Transform::try_from(value).unwrap() as u8, value);
+    }
+    assert!(Transform::try_from(0).is_err());
+    assert!(Transform::try_from(8).is_err());
+}
@@ -3,6 +3,70 @@
 #include <stdint.h>
 #include <stdlib.h>
 
+/**
+ * The color model of an encoded image, i.e. before decoders convert it to RGB(A).
+ */
+enum ColorType {
+  Gray = 1,
+  GrayAlpha,
+  RGB,
+  RGBA,
+  /**
+   * Palette based, e.g. GIFs
+   */
+  Indexed,
+  CMYK,
+};
+typedef uint8_t ColorType;
+
+/**
+ * The resampling filter used to resize images.
+ */
+enum Filter {
+  /**
+   * Nearest neighbor
+   */
+  Point = 1,
+  /**
+   * Bilinear
+   */
+  Triangle = 2,
+  /**
+   * Catmull-Rom (bicubic), sharp when upscaling and downscaling
+   */
+  CatmullRom = 3,
+  /**
+   * Mitchell-Netravali (bicubic), slightly smoother than Catmull-Rom
+   */
+  Mitchell = 4,
+  /**
+   * Lanczos with a window of 3
+   */
+  Lanczos3 = 5,
+};
+typedef uint8_t Filter;
+
+/**
+ * Where to place the crop window within the image, e.g. `North` to keep the top edge.
+ */
+enum Gravity {
+  Center = 0,
+  North = 1,
+  NorthEast = 2,
+  East = 3,
+  SouthEast = 4,
+  South = 5,
+  SouthWest = 6,
+  West = 7,
+  NorthWest = 8,
+  /**
+   * Place the crop window on the most interesting region, judged by its edge density,
+   * saturation and skin tones.
+   */
+  Attention = 9,
+};
+typedef uint8_t Gravity;
+
 enum ImageFormat {
   RGB8 = 1,
   RGBA8,
@@ -10,11 +74,95 @@ enum ImageFormat {
   PNG,
   AVIF,
   WEBP,
+  /**
+   * 16-bit samples in native byte order
+   */
+  RGB16,
+  /**
+   * 16-bit samples in native byte order
+   */
+  RGBA16,
+  GIF,
+  JXL,
+  TIFF,
+  ICO,
+  QOI,
+  PPM,
+  PAM,
 };
 typedef uint8_t ImageFormat;
 
+enum Preset {
+  /**
+   * Digital picture, like portrait, inner shot
+   */
+  Picture = 1,
+  /**
+   * Outdoor photograph, with natural lighting
+   */
+  Photo = 2,
+  /**
+   * Hand or line drawing, with high-contrast details
+   */
+  Drawing = 3,
+  /**
+   * Small-sized colorful images
+   */
+  Icon = 4,
+  /**
+   * Text-like
+   */
+  Text = 5,
+};
+typedef uint8_t Preset;
+
+/**
+ * A rotation or flip of an image.
+ */
+enum Transform {
+  /**
+   * Rotate by 90deg clockwise.
+   */
+  Rotate90 = 1,
+  /**
+   * Rotate by 180deg.
+   */
+  Rotate180 = 2,
+  /**
+   * Rotate by 270deg clockwise, i.e. by 90deg counterclockwise.
+   */
+  Rotate270 = 3,
+  /**
+   * Mirror the columns, i.e. swap left and right.
+   */
+  FlipHorizontal = 4,
+  /**
+   * Mirror the rows, i.e. swap top and bottom.
+   */
+  FlipVertical = 5,
+  /**
+   * Mirror along the top-left to bottom-right diagonal.
+   */
+  Transpose = 6,
+  /**
+   * Mirror along the top-right to bottom-left diagonal.
+   */
+  Transverse = 7,
+};
+typedef uint8_t Transform;
+
+/**
+ * A sequence of frames, each already composited onto the full canvas.
+ */
+typedef struct Animation Animation;
+
 typedef struct Context Context;
 
+/**
+ * Exif and XMP metadata of an image.
+ */
+typedef struct Metadata Metadata;
+
 typedef struct Image {
   uint8_t *ptr;
   uintptr_t len;
@@ -22,8 +170,48 @@ typedef struct Image {
   ImageFormat format;
   uint32_t width;
   uint32_t height;
+  /**
+   * Embedded ICC profile (null if there is none), see [`Image::icc_profile`].
+   */
+  uint8_t *icc_ptr;
+  uintptr_t icc_len;
+  /**
+   * Exif and XMP metadata (null if there is none), see [`Image::metadata`].
+   */
+  struct Metadata *metadata;
 } Image;
 
+/**
+ * The basic properties of an encoded image, see [`probe`].
+ */
+typedef struct ImageInfo {
+  /**
+   * The width of the decoded image, i.e. after decoders that apply the orientation (JPEG and
+   * JPEG XL) did so.
+   */
+  uint32_t width;
+  /**
+   * The height of the decoded image, see [`ImageInfo::width`].
+   */
+  uint32_t height;
+  ImageFormat format;
+  ColorType color_type;
+  /**
+   * Bits per sample
+   */
+  uint8_t bit_depth;
+  /**
+   * Whether the image has an alpha channel or a transparent color (which decoders expand into
+   * an alpha channel).
+   */
+  bool has_alpha;
+  /**
+   * The orientation (1-8, as defined by Exif) stored in the image, 1 if there is none. AVIF
+   * transformations (`irot` and `imir`) are not considered.
+   */
+  uint8_t orientation;
+} ImageInfo;
+
 struct Context *context_new(void);
 
 void context_drop(struct Context *img);
@@ -36,6 +224,25 @@ struct Image *image_new(void);
 
 void image_drop(struct Image *img);
 
+/**
+ * Drop all Exif and XMP metadata of `img`, except for the `len` Exif tags at `tags` (e.g. `0x8298`
+ * for the copyright notice, or `0x8825` for the GPS data) and the XMP packet if `keep_xmp` is set.
+ * Pass no tags and `false` to strip all metadata.
+ */
+int32_t image_retain_metadata(struct Image *img,
+                              const uint16_t *tags,
+                              uintptr_t len,
+                              bool keep_xmp);
+
+struct Animation *animation_new(uint32_t width, uint32_t height, uint32_t loop_count);
+
+void animation_drop(struct Animation *anim);
+
+/**
+ * Append a copy of `img` as a new frame, displayed for `delay` milliseconds.
+ */
+int32_t animation_push_frame(struct Animation *anim, struct Image *img, uint32_t delay);
+
 int32_t resize(struct Context *ctx,
                struct Image *img,
                uint32_t new_width,
@@ -43,6 +250,62 @@ int32_t resize(struct Context *ctx,
                bool maintain_aspect,
                struct Image *out);
 
+/**
+ * Set where to crop images whose aspect ratio is changed by `resize` (see `Gravity`). Unsets the
+ * focal point. Invalid values are ignored.
+ */
+void resize_set_gravity(struct Context *ctx, uint8_t gravity);
+
+/**
+ * Set the point (`x` and `y` normalized to 0..1) to keep in the center of images cropped by
+ * `resize`. Takes precedence over the gravity, until `resize_set_gravity` is called.
+ */
+void resize_set_focal_point(struct Context *ctx, double x, double y);
+
+/**
+ * Set the resampling filter used by `resize` (see `Filter`, defaults to `Triangle`). Invalid
+ * values are ignored.
+ */
+void resize_set_filter(struct Context *ctx, uint8_t filter);
+
+/**
+ * Crop the image to the `width`x`height` rectangle at `x`/`y` (from the top left).
+ */
+int32_t crop(struct Context *ctx,
+             struct Image *img,
+             uint32_t x,
+             uint32_t y,
+             uint32_t width,
+             uint32_t height,
+             struct Image *out);
+
+/**
+ * Rotate or flip the image, as given by `transform` (see `Transform`).
+ */
+int32_t transform(struct Context *ctx, struct Image *img, uint8_t transform, struct Image *out);
+
+#if defined(WIMG_COLOR)
+/**
+ * Convert the image from the color space of its ICC profile to sRGB.
+ */
+int32_t to_srgb(struct Context *ctx, struct Image *img, struct Image *out);
+#endif
+
+/**
+ * Detect the format of an encoded image. Returns `0` if the format is unknown.
+ */
+uint8_t detect_format(const uint8_t *ptr, uintptr_t size);
+
+/**
+ * Decode an image of any supported format, detected from its magic bytes.
+ */
+int32_t decode(struct Context *ctx, const uint8_t *ptr, uintptr_t size, struct Image *out);
+
+/**
+ * Read the basic properties of an image of any supported format into `out`, without decoding it.
+ */
+int32_t probe(struct Context *ctx, const uint8_t *ptr, uintptr_t size, struct ImageInfo *out);
+
 uint64_t hash(uint8_t *ptr, uintptr_t size, uint32_t seed);
 
 uint32_t jpeg_seed(void);
@@ -53,14 +316,44 @@ int32_t jpeg_encode(struct Context *ctx, struct Image *img, struct Image *out);
 
 void jpeg_set_encode_quality(struct Context *ctx, uint16_t quality);
 
+#if defined(WIMG_COLOR)
+void jpeg_set_decode_to_srgb(struct Context *ctx, bool to_srgb);
+#endif
+
+/**
+ * Keep the `len` Exif tags at `tags` and the XMP packet (if `keep_xmp` is set) when decoding
+ * JPEGs. All other metadata is dropped, by default all of it.
+ */
+void jpeg_set_decode_keep_metadata(struct Context *ctx,
+                                   const uint16_t *tags,
+                                   uintptr_t len,
+                                   bool keep_xmp);
+
 uint32_t png_seed(void);
 
 int32_t png_decode(struct Context *ctx, const uint8_t *ptr, uintptr_t size, struct Image *out);
 
 int32_t png_encode(struct Context *ctx, struct Image *img, struct Image *out);
 
+void png_set_decode_keep_16bit(struct Context *ctx, bool keep_16bit);
+
+#if defined(WIMG_COLOR)
+void png_set_decode_to_srgb(struct Context *ctx, bool to_srgb);
+#endif
+
+/**
+ * Keep the `len` Exif tags at `tags` and the XMP packet (if `keep_xmp` is set) when decoding
+ * PNGs. All other metadata is dropped, by default all of it.
+ */
+void png_set_decode_keep_metadata(struct Context *ctx,
+                                  const uint16_t *tags,
+                                  uintptr_t len,
+                                  bool keep_xmp);
+
 uint32_t avif_seed(void);
 
+int32_t avif_decode(struct Context *ctx, const uint8_t *ptr, uintptr_t size, struct Image *out);
+
 int32_t avif_encode(struct Context *ctx, struct Image *img, struct Image *out);
 
 void avif_set_encode_quality(struct Context *ctx, uint16_t quality);
@@ -69,6 +362,93 @@ void avif_set_encode_speed(struct Context *ctx, uint8_t speed);
 
 uint32_t webp_seed(void);
 
+int32_t webp_decode(struct Context *ctx, const uint8_t *ptr, uintptr_t size, struct Image *out);
+
 int32_t webp_encode(struct Context *ctx, struct Image *img, struct Image *out);
 
+int32_t webp_encode_animation(struct Context *ctx, struct Animation *anim, struct Image *out);
+
 void webp_set_encode_quality(struct Context *ctx, uint16_t quality);
+
+void webp_set_encode_lossless(struct Context *ctx, bool lossless);
+
+void webp_set_encode_near_lossless(struct Context *ctx, uint8_t near_lossless);
+
+void webp_set_encode_exact(struct Context *ctx, bool exact);
+
+void webp_set_encode_method(struct Context *ctx, uint8_t method);
+
+/**
+ * Set the preset (see `Preset`) to base the WebP encoding settings on. Invalid values are
+ * ignored.
+ */
+void webp_set_encode_preset(struct Context *ctx, uint8_t preset);
+
+void webp_set_encode_alpha_quality(struct Context *ctx, uint16_t alpha_quality);
+
+void webp_set_encode_use_sharp_yuv(struct Context *ctx, bool use_sharp_yuv);
+
+int32_t gif_decode(struct Context *ctx, const uint8_t *ptr, uintptr_t size, struct Image *out);
+
+int32_t gif_decode_animation(struct Context *ctx,
+                             const uint8_t *ptr,
+                             uintptr_t size,
+                             struct Animation *out);
+
+uint32_t jxl_seed(void);
+
+int32_t jxl_decode(struct Context *ctx, const uint8_t *ptr, uintptr_t size, struct Image *out);
+
+int32_t jxl_encode(struct Context *ctx, struct Image *img, struct Image *out);
+
+void jxl_set_encode_quality(struct Context *ctx, uint16_t quality);
+
+void jxl_set_encode_effort(struct Context *ctx, uint8_t effort);
+
+void jxl_set_encode_lossless(struct Context *ctx, bool lossless);
+
+void jxl_set_encode_threads(struct Context *ctx, uint8_t threads);
+
+int32_t tiff_decode(struct Context *ctx, const uint8_t *ptr, uintptr_t size, struct Image *out);
+
+/**
+ * Returns the number of pages of the TIFF, or a negative error code.
+ */
+int32_t tiff_page_count(struct Context *ctx, const uint8_t *ptr, uintptr_t size);
+
+void tiff_set_decode_page(struct Context *ctx, uint32_t page);
+
+void tiff_set_decode_keep_16bit(struct Context *ctx, bool keep_16bit);
+
+#if defined(WIMG_COLOR)
+void tiff_set_decode_to_srgb(struct Context *ctx, bool to_srgb);
+#endif
+
+uint32_t ico_seed(void);
+
+/**
+ * Encode the `len` images pointed to by `imgs` into a single multi-resolution ICO.
+ */
+int32_t ico_encode(struct Context *ctx,
+                   struct Image *const *imgs,
+                   uintptr_t len,
+                   struct Image *out);
+
+uint32_t qoi_seed(void);
+
+int32_t qoi_decode(struct Context *ctx, const uint8_t *ptr, uintptr_t size, struct Image *out);
+
+int32_t qoi_encode(struct Context *ctx, struct Image *img, struct Image *out);
+
+uint32_t pnm_seed(void);
+
+/**
+ * Decode a binary PGM, PPM or PAM.
+ */
+int32_t pnm_decode(struct Context *ctx, const uint8_t *ptr, uintptr_t size, struct Image *out);
+
+int32_t ppm_encode(struct Context *ctx, struct Image *img, struct Image *out);
+
+int32_t pam_encode(struct Context *ctx, struct Image *img, struct Image *out);
+
+void pnm_set_decode_keep_16bit(struct Context *ctx, bool keep_16bit);
@@ -2,7 +2,16 @@ export function decode(
   wimg: WImg,
   ctx: number,
   image: ArrayBuffer,
-  format: "jpeg" | "png"
+  format?:
+    | "jpeg"
+    | "png"
+    | "avif"
+    | "webp"
+    | "gif"
+    | "jxl"
+    | "tiff"
+    | "qoi"
+    | "pnm"
 ): Image {
   // allocate memory for input image
   const inData = wimg.alloc(image.byteLength);
@@ -17,7 +26,7 @@ export function decode(
   const outImg = wimg.image_new();
 
   // decode and dealloc input image
-  const errorCode = wimg[`${format}_decode`](
+  const errorCode = wimg[format ? `${format}_decode` : "decode"](
     ctx,
     inData,
     image.byteLength,
@@ -32,6 +41,50 @@ export function decode(
   return new Image(wimg, outImg);
 }
 
+export interface ImageInfo {
+  width: number;
+  height: number;
+  format: number;
+  colorType: number;
+  bitDepth: number;
+  hasAlpha: boolean;
+  orientation: number;
+}
+
+export function probe(wimg: WImg, ctx: number, image: ArrayBuffer): ImageInfo {
+  // allocate memory for input image and the info (16 bytes, see `ImageInfo` in wimg.h)
+  const inData = wimg.alloc(image.byteLength);
+  new Uint8ClampedArray(wimg.memory.buffer, inData, image.byteLength).set(
+    new Uint8Array(image)
+  );
+  const outInfo = wimg.alloc(16);
+
+  const errorCode = wimg.probe(ctx, inData, image.byteLength, outInfo);
+  wimg.dealloc(inData, image.byteLength);
+  if (errorCode < 0) {
+    wimg.dealloc(outInfo, 16);
+    throwLastError(wimg, ctx, errorCode);
+  }
+
+  const [width, height] = new Uint32Array(wimg.memory.buffer, outInfo, 2);
+  const [format, colorType, bitDepth, hasAlpha, orientation] = new Uint8Array(
+    wimg.memory.buffer,
+    outInfo + 8,
+    5
+  );
+  wimg.dealloc(outInfo, 16);
+
+  return {
+    width,
+    height,
+    format,
+    colorType,
+    bitDepth,
+    hasAlpha: hasAlpha !== 0,
+    orientation,
+  };
+}
+
 export function resize(
   wimg: WImg,
   ctx: number,
@@ -60,11 +113,127 @@ export function resize(
   return new Image(wimg, outImg);
 }
 
+export function crop(
+  wimg: WImg,
+  ctx: number,
+  img: Image,
+  x: number,
+  y: number,
+  width: number,
+  height: number
+): Image {
+  // allocate output image
+  const outImg = wimg.image_new();
+
+  // crop image
+  const errorCode = wimg.crop(ctx, img.ptr, x, y, width, height, outImg);
+  if (errorCode < 0) {
+    wimg.image_drop(outImg);
+    throwLastError(wimg, ctx, errorCode);
+  }
+
+  return new Image(wimg, outImg);
+}
+
+const TRANSFORMS = {
+  rotate90: 1,
+  rotate180: 2,
+  rotate270: 3,
+  flipHorizontal: 4,
+  flipVertical: 5,
+  transpose: 6,
+  transverse: 7,
+};
+
+export function transform(
+  wimg: WImg,
+  ctx: number,
+  img: Image,
+  transform: keyof typeof TRANSFORMS
+): Image {
+  // allocate output image
+  const outImg = wimg.image_new();
+
+  // rotate or flip image
+  const errorCode = wimg.transform(
+    ctx,
+    img.ptr,
+    TRANSFORMS[transform],
+    outImg
+  );
+  if (errorCode < 0) {
+    wimg.image_drop(outImg);
+    throwLastError(wimg, ctx, errorCode);
+  }
+
+  return new Image(wimg, outImg);
+}
+
+export function toSrgb(wimg: WImg, ctx: number, img: Image): Image {
+  if (!wimg.to_srgb) {
+    throw new Error("wimg was built without the `color` feature");
+  }
+
+  // allocate output image
+  const outImg = wimg.image_new();
+
+  // convert image
+  const errorCode = wimg.to_srgb(ctx, img.ptr, outImg);
+  if (errorCode < 0) {
+    wimg.image_drop(outImg);
+    throwLastError(wimg, ctx, errorCode);
+  }
+
+  return new Image(wimg, outImg);
+}
+
+export function retainMetadata(
+  wimg: WImg,
+  img: Image,
+  exifTags: number[],
+  keepXmp: boolean
+) {
+  if (exifTags.length === 0) {
+    wimg.image_retain_metadata(img.ptr, 0, 0, keepXmp);
+    return;
+  }
+
+  // allocate and write the tags (u16 each)
+  const length = exifTags.length * 2;
+  const tags = wimg.alloc(length);
+  new Uint16Array(wimg.memory.buffer, tags, exifTags.length).set(exifTags);
+
+  wimg.image_retain_metadata(img.ptr, tags, exifTags.length, keepXmp);
+  wimg.dealloc(tags, length);
+}
+
+export function keepMetadata(
+  wimg: WImg,
+  ctx: number,
+  exifTags: number[],
+  keepXmp: boolean
+) {
+  if (exifTags.length === 0) {
+    wimg.jpeg_set_decode_keep_metadata(ctx, 0, 0, keepXmp);
+    wimg.png_set_decode_keep_metadata(ctx, 0, 0, keepXmp);
+    return;
+  }
+
+  // allocate and write the tags (u16 each)
+  const length = exifTags.length * 2;
+  const tags = wimg.alloc(length);
+  new Uint16Array(wimg.memory.buffer, tags, exifTags.length).set(exifTags);
+
+  wimg.jpeg_set_decode_keep_metadata(ctx, tags, exifTags.length, keepXmp);
+  wimg.png_set_decode_keep_metadata(ctx, tags, exifTags.length, keepXmp);
+  wimg.dealloc(tags, length);
+}
+
 export function encode(
   wimg: WImg,
   ctx: number,
   img: Image,
-  format: "jpeg" | "png" | "avif" | "webp"
+  format: "jpeg" | "png" | "avif" | "webp" | "jxl" | "qoi" | "ppm" | "pam"
 ): Image {
   // allocate output image
   const outImg = wimg.image_new();
@@ -121,6 +290,20 @@ export class Image {
     return new Uint8Array(this.module.memory.buffer, offset, length);
   }
 
+  public iccProfile(): Uint8Array | undefined {
+    // `icc_ptr` and `icc_len` follow ptr, len, cap, format, width and height
+    const [offset, length] = new Uint32Array(
+      this.module.memory.buffer,
+      this.ptr + 24,
+      2
+    );
+    if (offset === 0) {
+      return undefined;
+    }
+
+    return new Uint8Array(this.module.memory.buffer, offset, length);
+  }
+
   public drop() {
     this.module.image_drop(this.ptr);
   }
@@ -131,6 +314,7 @@ export type Ptr = number;
 export type ContextPtr = Ptr;
 export type StringPtr = Ptr;
 export type ImagePtr = Ptr;
+export type AnimationPtr = Ptr;
 
 export interface WImg {
   readonly memory: WebAssembly.Memory;
@@ -146,6 +330,46 @@ export interface WImg {
 
   image_new(): ImagePtr;
   image_drop(ptr: ImagePtr): void;
+  image_retain_metadata(
+    img: ImagePtr,
+    tags: Ptr,
+    length: number,
+    keepXmp: boolean
+  ): ErrorCode;
+  jpeg_set_decode_keep_metadata(
+    ctx: ContextPtr,
+    tags: Ptr,
+    length: number,
+    keepXmp: boolean
+  ): void;
+  png_set_decode_keep_metadata(
+    ctx: ContextPtr,
+    tags: Ptr,
+    length: number,
+    keepXmp: boolean
+  ): void;
+
+  animation_new(
+    width: number,
+    height: number,
+    loopCount: number
+  ): AnimationPtr;
+  animation_drop(ptr: AnimationPtr): void;
+  animation_push_frame(
+    anim: AnimationPtr,
+    img: ImagePtr,
+    delay: number
+  ): ErrorCode;
+
+  detect_format(ptr: Ptr, length: number): number;
+  decode(
+    ctx: ContextPtr,
+    ptr: Ptr,
+    length: number,
+    out: ImagePtr
+  ): ErrorCode;
+
+  probe(ctx: ContextPtr, ptr: Ptr, length: number, out: Ptr): ErrorCode;
 
   resize(
     ctx: ContextPtr,
@@ -156,6 +380,26 @@ export interface WImg {
     out: ImagePtr
   ): ErrorCode;
 
+  crop(
+    ctx: ContextPtr,
+    img: ImagePtr,
+    x: number,
+    y: number,
+    width: number,
+    height: number,
+    out: ImagePtr
+  ): ErrorCode;
+
+  transform(
+    ctx: ContextPtr,
+    img: ImagePtr,
+    transform: number,
+    out: ImagePtr
+  ): ErrorCode;
+
+  // only available when built with the `color` feature
+  to_srgb?(ctx: ContextPtr, img: ImagePtr, out: ImagePtr): ErrorCode;
+
   jpeg_decode(
     ctx: ContextPtr,
     ptr: Ptr,
@@ -172,7 +416,77 @@ export interface WImg {
   ): ErrorCode;
   png_encode(ctx: ContextPtr, img: ImagePtr, out: ImagePtr): ErrorCode;
 
+  avif_decode(
+    ctx: ContextPtr,
+    ptr: Ptr,
+    length: number,
+    out: ImagePtr
+  ): ErrorCode;
   avif_encode(ctx: ContextPtr, img: ImagePtr, out: ImagePtr): ErrorCode;
 
+  webp_decode(
+    ctx: ContextPtr,
+    ptr: Ptr,
+    length: number,
+    out: ImagePtr
+  ): ErrorCode;
   webp_encode(ctx: ContextPtr, img: ImagePtr, out: ImagePtr): ErrorCode;
+  webp_encode_animation(
+    ctx: ContextPtr,
+    anim: AnimationPtr,
+    out: ImagePtr
+  ): ErrorCode;
+
+  gif_decode(
+    ctx: ContextPtr,
+    ptr: Ptr,
+    length: number,
+    out: ImagePtr
+  ): ErrorCode;
+  gif_decode_animation(
+    ctx: ContextPtr,
+    ptr: Ptr,
+    length: number,
+    out: AnimationPtr
+  ): ErrorCode;
+
+  jxl_decode(
+    ctx: ContextPtr,
+    ptr: Ptr,
+    length: number,
+    out: ImagePtr
+  ): ErrorCode;
+  jxl_encode(ctx: ContextPtr, img: ImagePtr, out: ImagePtr): ErrorCode;
+
+  tiff_decode(
+    ctx: ContextPtr,
+    ptr: Ptr,
+    length: number,
+    out: ImagePtr
+  ): ErrorCode;
+  tiff_page_count(ctx: ContextPtr, ptr: Ptr, length: number): number;
+
+  ico_encode(
+    ctx: ContextPtr,
+    imgs: Ptr,
+    length: number,
+    out: ImagePtr
+  ): ErrorCode;
+
+  qoi_decode(
+    ctx: ContextPtr,
+    ptr: Ptr,
+    length: number,
+    out: ImagePtr
+  ): ErrorCode;
+  qoi_encode(ctx: ContextPtr, img: ImagePtr, out: ImagePtr): ErrorCode;
+
+  pnm_decode(
+    ctx: ContextPtr,
+    ptr: Ptr,
+    length: number,
+    out: ImagePtr
+  ): ErrorCode;
+  ppm_encode(ctx: ContextPtr, img: ImagePtr, out: ImagePtr): ErrorCode;
+  pam_encode(ctx: ContextPtr, img: ImagePtr, out: ImagePtr): ErrorCode;
 }